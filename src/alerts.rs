@@ -0,0 +1,396 @@
+//! Alert rules over snapshot fields, with hysteresis and webhook delivery. A rule is one comparison on
+//! any scalar `/api` field, optionally held for a duration (`tip_width >= 20 for 30s`,
+//! `connected == false for 60s`, `template_limit == "cap"`). It goes pending when the condition first
+//! holds, fires once it has held for the duration, and resolves when its `resolve` condition (default:
+//! the `when` condition no longer holding) has held for that one's duration. Firing and resolving are
+//! POSTed as JSON to every `alert_webhooks` URL (plain `http://`, raw tokio like the dashboard server),
+//! retried a few times, and never block ingestion.
+
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::config::Config;
+use crate::engine::Snapshot;
+
+const ATTEMPTS: u32 = 3; // per webhook per notification, 1 s then 2 s apart
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One `[[alerts]]` table in config.toml.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AlertRule {
+    pub name: String,
+    /// `<field> <op> <value> [for <duration>]`; op is one of == != >= <= > <, value a number, true/false
+    /// or a quoted string, duration like 500ms, 30s, 5m, 1h.
+    pub when: String,
+    /// Condition that resolves a firing alert, same syntax (empty = `when` no longer holds).
+    #[serde(default)]
+    pub resolve: String,
+    #[serde(default = "default_severity")]
+    pub severity: String,
+}
+
+fn default_severity() -> String { "warning".to_string() }
+
+/// A rule's state as served in the snapshot (`alerts`).
+#[derive(Serialize, Clone, Default)]
+pub struct AlertStatus {
+    pub name: String,
+    pub severity: String,
+    pub state: &'static str, // "ok" | "pending" | "firing" | "resolving"
+    pub since_ms: i64,       // when it entered that state (0 = never left ok)
+    pub value: Value,        // at the last evaluation, of the field the active condition tests
+}
+
+struct Cond {
+    field: String,
+    op: Op,
+    value: Value,
+    hold_ms: i64,
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Ge,
+    Le,
+    Gt,
+    Lt,
+}
+
+struct Alert {
+    rule: AlertRule,
+    when: Cond,
+    resolve: Option<Cond>,
+    state: &'static str,
+    since_ms: i64,
+    fired_ms: i64,
+    value: Value,
+}
+
+pub struct Alerts {
+    alerts: Vec<Alert>,
+    webhooks: Vec<String>,
+}
+
+impl Alerts {
+    /// Parse the configured rules, checking every field exists in a snapshot and is a scalar.
+    pub fn new(cfg: &Config) -> Result<Self> {
+        let sample = serde_json::to_value(Snapshot::default())?;
+        let mut alerts = Vec::new();
+        for rule in &cfg.alerts {
+            let ctx = |e: anyhow::Error| anyhow!("alert {:?}: {e}", rule.name);
+            let when = Cond::parse(&rule.when, &sample).map_err(ctx)?;
+            let resolve = match rule.resolve.trim() {
+                "" => None,
+                r => Some(Cond::parse(r, &sample).map_err(ctx)?),
+            };
+            alerts.push(Alert { rule: rule.clone(), when, resolve, state: "ok", since_ms: 0, fired_ms: 0, value: Value::Null });
+        }
+        for url in &cfg.alert_webhooks {
+            split_url(url)?;
+        }
+        Ok(Self { alerts, webhooks: cfg.alert_webhooks.clone() })
+    }
+
+    /// Evaluate every rule against `s` at `now_ms`, sending notifications for those that fire or resolve.
+    /// While a reachable node is still syncing nothing new goes pending and nothing firing resolves: its
+    /// numbers describe the catch-up, not the network.
+    pub fn observe(&mut self, s: &Snapshot, now_ms: i64) {
+        let Ok(v) = serde_json::to_value(s) else { return };
+        let live = s.synced || !s.connected;
+        for a in &mut self.alerts {
+            let held = live && a.when.test(&v);
+            let cleared = live && a.resolve.as_ref().map(|r| r.test(&v)).unwrap_or(!held);
+            let hold = a.resolve.as_ref().map(|r| r.hold_ms).unwrap_or(0);
+            let event = match a.state {
+                "ok" if held => a.enter("pending", now_ms),
+                "pending" if !held => a.enter("ok", now_ms),
+                "firing" if cleared => a.enter("resolving", now_ms),
+                "resolving" if !cleared => a.enter("firing", a.fired_ms),
+                _ => None,
+            };
+            let event = event.or_else(|| match a.state {
+                "pending" if now_ms - a.since_ms >= a.when.hold_ms => a.enter("firing", now_ms),
+                "resolving" if now_ms - a.since_ms >= hold => a.enter("ok", now_ms),
+                _ => None,
+            });
+            a.value = v.get(&a.active().field).cloned().unwrap_or(Value::Null);
+            if let Some(kind) = event {
+                let body = serde_json::json!({
+                    "alert": a.rule.name, "status": kind, "severity": a.rule.severity, "when": a.rule.when,
+                    "resolve": a.rule.resolve, "field": a.active().field, "value": a.value, "fired_ms": a.fired_ms,
+                    "network": s.network, "t_ms": now_ms,
+                });
+                log::warn!("alert {} {kind}: {} = {}", a.rule.name, a.active().field, a.value);
+                for url in &self.webhooks {
+                    tokio::spawn(deliver(url.clone(), body.to_string()));
+                }
+            }
+        }
+    }
+
+    pub fn status(&self) -> Vec<AlertStatus> {
+        self.alerts
+            .iter()
+            .map(|a| AlertStatus {
+                name: a.rule.name.clone(),
+                severity: a.rule.severity.clone(),
+                state: a.state,
+                since_ms: a.since_ms,
+                value: a.value.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Alert {
+    /// The condition the current state waits on: `when` until the alert fires, then `resolve` (if set).
+    fn active(&self) -> &Cond {
+        match self.state {
+            "firing" | "resolving" => self.resolve.as_ref().unwrap_or(&self.when),
+            _ => &self.when,
+        }
+    }
+
+    /// Move to `state` from `since_ms`; returns the notification this transition sends, if any.
+    fn enter(&mut self, state: &'static str, since_ms: i64) -> Option<&'static str> {
+        let from = std::mem::replace(&mut self.state, state);
+        self.since_ms = since_ms;
+        match (from, state) {
+            ("pending", "firing") => {
+                self.fired_ms = since_ms;
+                Some("firing")
+            }
+            ("resolving", "ok") => Some("resolved"),
+            _ => None,
+        }
+    }
+}
+
+impl Cond {
+    fn parse(src: &str, sample: &Value) -> Result<Self> {
+        let (expr, hold_ms) = match src.rsplit_once(" for ") {
+            Some((e, d)) => (e, duration_ms(d.trim())?),
+            None => (src, 0),
+        };
+        let mut it = expr.split_whitespace();
+        let (Some(field), Some(op), Some(_)) = (it.next(), it.next(), it.clone().next()) else {
+            bail!("expected `<field> <op> <value> [for <duration>]`, got {src:?}");
+        };
+        let raw = it.collect::<Vec<_>>().join(" ");
+        let op = match op {
+            "==" => Op::Eq,
+            "!=" => Op::Ne,
+            ">=" => Op::Ge,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            "<" => Op::Lt,
+            _ => bail!("unknown operator {op:?}"),
+        };
+        let value = match raw.as_str() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            r if r.len() >= 2 && r.starts_with('"') && r.ends_with('"') => Value::String(r[1..r.len() - 1].to_string()),
+            r => Value::from(r.parse::<f64>().map_err(|_| anyhow!("bad value {r:?}"))?),
+        };
+        match sample.get(field) {
+            None => bail!("no snapshot field {field:?}"),
+            Some(Value::Array(_) | Value::Object(_)) => bail!("{field:?} is not a scalar"),
+            Some(_) => {}
+        }
+        if !value.is_number() && !matches!(op, Op::Eq | Op::Ne) {
+            bail!("{raw} can only be compared with == or !=");
+        }
+        Ok(Self { field: field.to_string(), op, value, hold_ms })
+    }
+
+    fn test(&self, snap: &Value) -> bool {
+        let Some(v) = snap.get(&self.field) else { return false };
+        match (v.as_f64(), self.value.as_f64()) {
+            (Some(a), Some(b)) => match self.op {
+                Op::Eq => a == b,
+                Op::Ne => a != b,
+                Op::Ge => a >= b,
+                Op::Le => a <= b,
+                Op::Gt => a > b,
+                Op::Lt => a < b,
+            },
+            _ => match self.op {
+                Op::Eq => *v == self.value,
+                Op::Ne => *v != self.value,
+                _ => false,
+            },
+        }
+    }
+}
+
+fn duration_ms(d: &str) -> Result<i64> {
+    let split = d.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(d.len());
+    let (n, unit) = d.split_at(split);
+    let n: f64 = n.parse().map_err(|_| anyhow!("bad duration {d:?}"))?;
+    let scale = match unit {
+        "ms" => 1.0,
+        "s" | "" => 1000.0,
+        "m" => 60_000.0,
+        "h" => 3_600_000.0,
+        _ => bail!("bad duration unit in {d:?}"),
+    };
+    Ok((n * scale) as i64)
+}
+
+/// `http://host[:port][/path]` → (host:port, path).
+fn split_url(url: &str) -> Result<(String, String)> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| anyhow!("webhook {url:?}: only http:// is supported"))?;
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if host.is_empty() {
+        bail!("webhook {url:?}: no host");
+    }
+    let host = if host.contains(':') { host.to_string() } else { format!("{host}:80") };
+    Ok((host, path.to_string()))
+}
+
+async fn deliver(url: String, body: String) {
+    for attempt in 0..ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_secs(1 << (attempt - 1))).await;
+        }
+        match tokio::time::timeout(WEBHOOK_TIMEOUT, post(&url, &body)).await {
+            Ok(Ok(())) => return,
+            Ok(Err(e)) => log::warn!("webhook {url}: {e}"),
+            Err(_) => log::warn!("webhook {url}: timed out"),
+        }
+    }
+    log::error!("webhook {url}: giving up after {ATTEMPTS} attempts");
+}
+
+/// POST `body` as JSON and require a 2xx status.
+pub async fn post(url: &str, body: &str) -> Result<()> {
+    let (host, path) = split_url(url)?;
+    let mut sock = TcpStream::connect(&host).await?;
+    let req = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    sock.write_all(req.as_bytes()).await?;
+    let mut buf = [0u8; 512];
+    let n = sock.read(&mut buf).await?;
+    let head = String::from_utf8_lossy(&buf[..n]);
+    let status = head.split_whitespace().nth(1).unwrap_or("");
+    if !status.starts_with('2') {
+        bail!("HTTP {}", if status.is_empty() { "no response" } else { status });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn alerts(rules: &str, webhooks: &[String]) -> Alerts {
+        let mut cfg: Config = toml::from_str(&format!("rpc_url = \"\"\n{rules}")).unwrap();
+        cfg.alert_webhooks = webhooks.to_vec();
+        Alerts::new(&cfg).unwrap()
+    }
+
+    fn snap(tips: usize) -> Snapshot {
+        Snapshot { tip_width: tips, connected: true, synced: true, ..Default::default() }
+    }
+
+    #[test]
+    fn parses_rules() {
+        let sample = serde_json::to_value(Snapshot::default()).unwrap();
+        let c = Cond::parse("tip_width >= 20 for 30s", &sample).unwrap();
+        assert_eq!((c.field.as_str(), c.value.as_f64(), c.hold_ms), ("tip_width", Some(20.0), 30_000));
+        assert_eq!(Cond::parse("connected == false for 1m", &sample).unwrap().value, Value::Bool(false));
+        assert_eq!(Cond::parse(r#"template_limit == "cap""#, &sample).unwrap().value, Value::from("cap"));
+        assert_eq!(Cond::parse("bps < 1.5 for 500ms", &sample).unwrap().hold_ms, 500);
+
+        assert!(Cond::parse("no_such_field > 1", &sample).is_err());
+        assert!(Cond::parse("tips > 1", &sample).is_err()); // not a scalar
+        assert!(Cond::parse("tip_width => 1", &sample).is_err());
+        assert!(Cond::parse("tip_width >=", &sample).is_err());
+        assert!(Cond::parse(r#"template_limit >= "cap""#, &sample).is_err());
+        assert!(Cond::parse("tip_width >= 20 for 3 days", &sample).is_err());
+        assert!(split_url("https://example.com/hook").is_err());
+        assert_eq!(split_url("http://10.0.0.1/x").unwrap(), ("10.0.0.1:80".to_string(), "/x".to_string()));
+    }
+
+    #[test]
+    fn pending_firing_resolved_with_hysteresis() {
+        let rules = "[[alerts]]\nname = \"wide\"\nwhen = \"tip_width >= 20 for 30s\"\nresolve = \"tip_width < 10 for 20s\"\n";
+        let mut a = alerts(rules, &[]);
+        let mut step = |tips: usize, t: i64| {
+            a.observe(&snap(tips), t);
+            let s = a.status().remove(0);
+            (s.state, s.since_ms)
+        };
+        assert_eq!(step(25, 0), ("pending", 0));
+        assert_eq!(step(25, 10_000), ("pending", 0));
+        assert_eq!(step(5, 15_000), ("ok", 15_000)); // dropped before the hold
+        assert_eq!(step(25, 20_000), ("pending", 20_000));
+        assert_eq!(step(25, 50_000), ("firing", 50_000));
+        assert_eq!(step(15, 60_000), ("firing", 50_000)); // under `when` but not yet `resolve`
+        assert_eq!(step(5, 70_000), ("resolving", 70_000));
+        assert_eq!(step(15, 80_000), ("firing", 50_000)); // relapsed: keeps the original fire time
+        assert_eq!(step(5, 90_000), ("resolving", 90_000));
+        assert_eq!(step(5, 110_000), ("ok", 110_000));
+
+        let mut syncing = snap(25);
+        syncing.synced = false;
+        a.observe(&syncing, 120_000);
+        assert_eq!(a.status()[0].state, "ok");
+    }
+
+    #[test]
+    fn value_follows_the_active_condition() {
+        let rules = "[[alerts]]\nname = \"wide\"\nwhen = \"tip_width >= 20\"\nresolve = \"blue_delta < 5\"\n";
+        let mut a = alerts(rules, &[]);
+        let mut s = Snapshot { blue_delta: 9, ..snap(25) };
+        a.observe(&s, 0);
+        let st = a.status().remove(0);
+        assert_eq!((st.state, st.value), ("firing", Value::from(9))); // waiting on `resolve`
+        s.blue_delta = 2;
+        a.observe(&s, 1_000);
+        let st = a.status().remove(0);
+        assert_eq!((st.state, st.value), ("ok", Value::from(25)));
+    }
+
+    #[tokio::test]
+    async fn firing_posts_the_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let mut a = alerts("[[alerts]]\nname = \"wide\"\nwhen = \"tip_width >= 20\"\n", &[url]);
+        a.observe(&snap(25), 1_000); // no hold: pending and firing at once
+        assert_eq!(a.status()[0].state, "firing");
+
+        let (mut sock, _) = tokio::time::timeout(Duration::from_secs(5), listener.accept()).await.unwrap().unwrap();
+        let (mut req, mut buf) = (Vec::new(), [0u8; 1024]);
+        let (line, body) = loop {
+            let n = sock.read(&mut buf).await.unwrap();
+            assert!(n > 0, "closed before the body arrived");
+            req.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&req);
+            if let Some((head, body)) = text.split_once("\r\n\r\n")
+                && let Ok(v) = serde_json::from_str::<Value>(body)
+            {
+                break (head.lines().next().unwrap_or("").to_string(), v);
+            }
+        };
+        sock.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await.unwrap();
+        assert_eq!(line, "POST /hook HTTP/1.1");
+        assert_eq!(body["alert"], "wide");
+        assert_eq!(body["status"], "firing");
+        assert_eq!(body["value"], 25);
+        assert_eq!(body["t_ms"], 1_000);
+    }
+}
@@ -0,0 +1,456 @@
+//! `stitchbot analyze [metrics.jsonl] [--json]` — the offline report over a metrics log. The question it
+//! answers: does *orphaned work* actually rise when tip width exceeds the parent-merge cap? If it doesn't,
+//! the "narrow the DAG to save work" thesis is refuted and the node-side mechanism isn't worth building; if
+//! it does, there's a measured efficiency gap. Statistics come from `engine.rs` and tip excess is the one
+//! the engine logged, so the report can't drift from the binary that produced the data.
+
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{correlation, mean, quantile, round2, round4};
+
+const MIN_ROWS: usize = 5;
+const RULE: usize = 62;
+
+/// The fields of a `log_metrics` record the report reads; anything missing reads as zero.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Row {
+    t: i64,
+    net: String,
+    tips: usize,
+    max_parents: usize,
+    tip_excess: usize,
+    red_rate: f64,
+    merge_lat_mean: f64,
+    merge_lat_max: u64,
+    merge_depth: u64,
+    depth_used_pct: f64,
+    conf_time_mean: f64,
+    conf_corr: f64,
+    conf_samples: usize,
+    fracture: bool,
+    fracture_secs: f64,
+}
+
+#[derive(Serialize)]
+pub struct Report {
+    pub path: String,
+    pub network: String,
+    pub samples: usize,
+    pub span_secs: f64,
+    pub tips: TipStats,
+    pub red_by_excess: Vec<RedBucket>, // orphan (red) rate, percent
+    pub red_by_width: Vec<RedBucket>,
+    pub red_over_cap: RedBucket, // every sample with tip excess
+    pub excess_red_corr: Option<f64>, // None when tip excess never varied
+    pub fracture: FractureStats,
+    pub merge_latency: Option<LatencyStats>,
+    pub headroom: Option<Headroom>,
+    pub confirmation: Option<Confirmation>,
+    pub verdict: &'static str, // "no_over_cap" | "refuted" | "signal" | "inconclusive"
+}
+
+#[derive(Serialize)]
+pub struct TipStats {
+    pub mean: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub max: usize,
+    pub widest_merge: usize, // most parents seen on one block: the DAG had at least that many tips once
+    pub histogram: BTreeMap<usize, usize>,
+    pub histogram_over_cap: BTreeMap<usize, usize>, // of those, samples past their own merge cap
+    pub over_cap_samples: usize,
+    pub over_cap_pct: f64,
+    pub max_excess: usize,
+}
+
+#[derive(Serialize)]
+pub struct RedBucket {
+    pub label: String,
+    pub samples: usize,
+    pub red_mean: Option<f64>,
+    pub red_max: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct FractureStats {
+    pub fractured_pct: f64,
+    pub episodes: usize,
+    pub longest_secs: f64,
+}
+
+#[derive(Serialize)]
+pub struct LatencyStats {
+    pub mean: f64,
+    pub worst: u64,
+    pub by_width: Vec<LatencyBand>,
+}
+
+#[derive(Serialize)]
+pub struct LatencyBand {
+    pub label: String,
+    pub samples: usize,
+    pub lag_mean: f64,
+    pub worst: u64,
+}
+
+#[derive(Serialize)]
+pub struct Headroom {
+    pub merge_depth: u64,
+    pub worst_pct: f64,
+    pub mean_pct: f64,
+    pub verdict: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct Confirmation {
+    pub mean_secs: f64,
+    pub corr: f64,
+    pub samples: usize,
+    pub run_mean_corr: f64,
+    pub link: &'static str,
+}
+
+pub fn run(default_path: &str, args: &[String]) -> Result<()> {
+    let path = args.iter().find(|a| !a.starts_with("--")).map(String::as_str).unwrap_or(default_path);
+    let report = analyze(path)?;
+    if args.iter().any(|a| a == "--json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print(&report);
+    }
+    Ok(())
+}
+
+fn load(path: &str) -> Result<Vec<Row>> {
+    let f = match std::fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => bail!("no metrics file at {path:?} — is the monitor running?"),
+        Err(e) => return Err(e.into()),
+    };
+    let mut rows = Vec::new();
+    for line in std::io::BufReader::new(f).lines() {
+        let line = line?;
+        if let Ok(r) = serde_json::from_str::<Row>(line.trim()) {
+            rows.push(r);
+        }
+    }
+    Ok(rows)
+}
+
+pub fn analyze(path: &str) -> Result<Report> {
+    let rows = load(path)?;
+    if rows.len() < MIN_ROWS {
+        bail!("only {} records — let the monitor collect more, then re-run", rows.len());
+    }
+    let n = rows.len();
+    let tips: Vec<f64> = rows.iter().map(|r| r.tips as f64).collect();
+    let red: Vec<f64> = rows.iter().map(|r| r.red_rate * 100.0).collect();
+    let excess: Vec<usize> = rows.iter().map(|r| r.tip_excess).collect();
+    let cap = rows.iter().map(|r| r.max_parents).max().unwrap_or(0);
+
+    // Every sample is judged against its own merge cap, as its tip excess already is: the cap moves, and
+    // one global cap would file a sample that was over the cap of its day under "within".
+    let (mut histogram, mut histogram_over_cap) = (BTreeMap::new(), BTreeMap::new());
+    for r in &rows {
+        *histogram.entry(r.tips).or_insert(0) += 1;
+        if r.tip_excess > 0 {
+            *histogram_over_cap.entry(r.tips).or_insert(0) += 1;
+        }
+    }
+    let over = excess.iter().filter(|&&e| e > 0).count();
+    let tip_stats = TipStats {
+        mean: round2(mean(&tips)),
+        median: quantile(&tips, 0.5),
+        p95: quantile(&tips, 0.95),
+        max: rows.iter().map(|r| r.tips).max().unwrap_or(0),
+        widest_merge: cap,
+        histogram,
+        histogram_over_cap,
+        over_cap_samples: over,
+        over_cap_pct: round2(100.0 * over as f64 / n as f64),
+        max_excess: excess.iter().copied().max().unwrap_or(0),
+    };
+
+    let bucket = |label: String, keep: &dyn Fn(usize) -> bool| {
+        let rr: Vec<f64> = (0..n).filter(|&i| keep(i)).map(|i| red[i]).collect();
+        RedBucket {
+            label,
+            samples: rr.len(),
+            red_mean: (!rr.is_empty()).then(|| round4(mean(&rr))),
+            red_max: rr.iter().copied().reduce(f64::max).map(round4),
+        }
+    };
+    let red_by_excess = vec![
+        bucket("0 (within cap)".into(), &|i| excess[i] == 0),
+        bucket("1–3 over".into(), &|i| (1..=3).contains(&excess[i])),
+        bucket("4–6 over".into(), &|i| (4..=6).contains(&excess[i])),
+        bucket("7+ over".into(), &|i| excess[i] >= 7),
+    ];
+    let bands = [(0, 0), (1, 4), (5, usize::MAX)];
+    let red_by_width = bands.iter().map(|&(lo, hi)| bucket(band(lo, hi), &|i| (lo..=hi).contains(&excess[i]))).collect();
+
+    let red_over_cap = bucket("over cap".into(), &|i| excess[i] > 0);
+
+    let distinct: std::collections::HashSet<usize> = excess.iter().copied().collect();
+    let pairs: Vec<(f64, f64)> = (0..n).map(|i| (excess[i] as f64, red[i])).collect();
+    let excess_red_corr = (distinct.len() > 1).then(|| round4(correlation(&pairs)));
+
+    let fracture = FractureStats {
+        fractured_pct: round2(100.0 * rows.iter().filter(|r| r.fracture).count() as f64 / n as f64),
+        episodes: rows.windows(2).filter(|w| w[1].fracture && !w[0].fracture).count(),
+        longest_secs: rows.iter().map(|r| r.fracture_secs).fold(0.0, f64::max),
+    };
+
+    let merge_latency = rows.iter().any(|r| r.merge_lat_max > 0).then(|| {
+        let by_width = [(0, 0), (1, usize::MAX)]
+            .iter()
+            .filter_map(|&(lo, hi)| {
+                let idx: Vec<&Row> = rows.iter().filter(|r| (lo..=hi).contains(&r.tip_excess)).collect();
+                (!idx.is_empty()).then(|| LatencyBand {
+                    label: band(lo, hi),
+                    samples: idx.len(),
+                    lag_mean: round2(mean(&idx.iter().map(|r| r.merge_lat_mean).collect::<Vec<_>>())),
+                    worst: idx.iter().map(|r| r.merge_lat_max).max().unwrap_or(0),
+                })
+            })
+            .collect();
+        LatencyStats {
+            mean: round2(mean(&rows.iter().map(|r| r.merge_lat_mean).collect::<Vec<_>>())),
+            worst: rows.iter().map(|r| r.merge_lat_max).max().unwrap_or(0),
+            by_width,
+        }
+    });
+
+    let md = rows.iter().map(|r| r.merge_depth).max().unwrap_or(0);
+    let dpu: Vec<f64> = rows.iter().map(|r| r.depth_used_pct).collect();
+    let headroom = (md > 0 && dpu.iter().any(|&d| d > 0.0)).then(|| {
+        let worst = dpu.iter().copied().fold(0.0, f64::max);
+        Headroom {
+            merge_depth: md,
+            worst_pct: round4(worst),
+            mean_pct: round4(mean(&dpu)),
+            verdict: match worst {
+                w if w < 20.0 => "ample headroom",
+                w if w < 70.0 => "WATCH — approaching the cliff",
+                _ => "CRITICAL — near the permanent-orphan cliff",
+            },
+        }
+    });
+
+    let have: Vec<&Row> = rows.iter().filter(|r| r.conf_samples >= 3).collect();
+    let confirmation = have.last().map(|latest| {
+        let ct: Vec<f64> = rows.iter().filter(|r| r.conf_samples > 0).map(|r| r.conf_time_mean).collect();
+        // only average correlations once enough blocks have confirmed (early small-n values are noisy)
+        let well: Vec<f64> = have.iter().filter(|r| r.conf_samples >= 50).map(|r| r.conf_corr).collect();
+        let cc = if well.is_empty() { have.iter().map(|r| r.conf_corr).collect() } else { well };
+        let r = latest.conf_corr;
+        Confirmation {
+            mean_secs: round2(mean(&ct)),
+            corr: round2(r),
+            samples: latest.conf_samples,
+            run_mean_corr: round2(mean(&cc)),
+            link: match r {
+                r if r.abs() < 0.2 => "no link — merge lag is negligible vs the confirmation depth",
+                r if r > 0.4 => "merge lag feeds through into confirmation time",
+                _ => "weak/uncertain link",
+            },
+        }
+    });
+
+    let (over_mean, over_max) = (red_over_cap.red_mean.unwrap_or(0.0), red_over_cap.red_max.unwrap_or(0.0));
+    let verdict = if over == 0 {
+        "no_over_cap"
+    } else if over_mean < 0.5 && over_max < 2.0 {
+        "refuted"
+    } else if excess_red_corr.is_some_and(|c| c > 0.3) && over_mean > 2.0 {
+        "signal"
+    } else {
+        "inconclusive"
+    };
+
+    Ok(Report {
+        path: path.to_string(),
+        network: rows.last().map(|r| r.net.clone()).unwrap_or_default(),
+        samples: n,
+        span_secs: (rows[n - 1].t - rows[0].t) as f64 / 1000.0,
+        tips: tip_stats,
+        red_by_excess,
+        red_by_width,
+        red_over_cap,
+        excess_red_corr,
+        fracture,
+        merge_latency,
+        headroom,
+        confirmation,
+        verdict,
+    })
+}
+
+fn print(r: &Report) {
+    let t = &r.tips;
+    let cap = t.widest_merge;
+    println!("{}", "═".repeat(RULE));
+    println!(" StitchBot DAG-health analysis · {} · {}", r.network, r.path);
+    println!(" {} samples over {}  ({:.1}s/sample)", r.samples, hms(r.span_secs), r.span_secs / r.samples.max(1) as f64);
+    println!("{}", "═".repeat(RULE));
+
+    println!("\nTIP WIDTH DISTRIBUTION");
+    let most = t.histogram.values().copied().max().unwrap_or(0);
+    let (lo, hi) = (t.histogram.keys().next().copied().unwrap_or(0), t.max);
+    for w in lo..=hi {
+        let c = t.histogram.get(&w).copied().unwrap_or(0);
+        let flag = match t.histogram_over_cap.get(&w).copied().unwrap_or(0) {
+            0 => String::new(),
+            k if k == c => "  ← over merge cap".to_string(),
+            k => format!("  ← {k} over merge cap"),
+        };
+        println!("  {w:>3} tips │ {:<34} {c:>6}{flag}", bar(c, most, 34));
+    }
+    println!("  mean {:.2} · median {:.0} · p95 {:.0} · max {} (polled)", t.mean, t.median, t.p95, t.max);
+    println!("  widest single-block merge: {cap} parents → the DAG reached ≥{cap} tips at least once");
+    println!("  (a k-parent block proves ≥k simultaneous tips — parent counts catch spikes the poll misses)");
+    println!("  samples with tips OVER the cap: {} ({:.1}%)  ·  max excess seen: {}", t.over_cap_samples, t.over_cap_pct, t.max_excess);
+
+    println!("\nTHE TEST — orphan (red) rate grouped by tip excess");
+    println!("  excess = tips beyond the merge cap (the only regime that can waste work)");
+    println!("  {:<16}{:>9}{:>14}{:>13}", "bucket", "samples", "mean orphan", "max orphan");
+    for b in &r.red_by_excess {
+        match (b.red_mean, b.red_max) {
+            (Some(m), Some(x)) => println!("  {:<16}{:>9}{:>13.3}%{:>12.3}%", b.label, b.samples, m, x),
+            _ => println!("  {:<16}{:>9}{:>14}{:>13}", b.label, 0, "—", "—"),
+        }
+    }
+    println!("\n  orphan rate by tip width over each sample's own cap:");
+    for b in &r.red_by_width {
+        if let (Some(m), Some(x)) = (b.red_mean, b.red_max) {
+            println!("    {:<14} mean {m:>7.3}%  max {x:>7.3}%  (n={})", b.label, b.samples);
+        }
+    }
+    if let Some(c) = r.excess_red_corr {
+        println!("\n  correlation(tip_excess, orphan_rate) = {c:+.3}");
+    }
+
+    let f = &r.fracture;
+    println!("\nFRACTURE STATE (mutually unordered high-blue blocks)");
+    println!("  fractured {:.1}% of samples · {} episodes · longest {}", f.fractured_pct, f.episodes, hms(f.longest_secs));
+
+    if let Some(l) = &r.merge_latency {
+        println!("\nMERGE LATENCY  (blue-score rounds a block waits to be merged — the conjecture test)");
+        println!("  overall mean {:.1} · worst single block {} rounds", l.mean, l.worst);
+        println!("  by tip width (does lag grow once tips exceed the cap?):");
+        for b in &l.by_width {
+            println!("    {:<14} mean-lag {:>6.1}  worst {:>5}  (n={})", b.label, b.lag_mean, b.worst, b.samples);
+        }
+    }
+
+    if let Some(h) = &r.headroom {
+        println!("\nMERGE-DEPTH HEADROOM  (cliff = {} rounds; blocks past it are orphaned forever)", h.merge_depth);
+        println!("  worst merge used {:.3}% of the budget · mean {:.3}%  → {}", h.worst_pct, h.mean_pct, h.verdict);
+    }
+
+    if let Some(c) = &r.confirmation {
+        println!("\nCONFIRMATION TIME vs MERGE LAG  (proxy: rounds-to-D below the frontier)");
+        println!("  confirmation time: mean {:.2}s", c.mean_secs);
+        println!("  corr(merge_lag, conf_time): {:+.2} (n={}) · run-mean {:+.2}  → {}", c.corr, c.samples, c.run_mean_corr, c.link);
+    }
+
+    println!("\n{}", "═".repeat(RULE));
+    let o = &r.red_over_cap;
+    let (n_over, over_mean, over_max) = (o.samples, o.red_mean.unwrap_or(0.0), o.red_max.unwrap_or(0.0));
+    let all_max = r.red_by_excess.iter().filter_map(|b| b.red_max).fold(0.0, f64::max);
+    match r.verdict {
+        "no_over_cap" => {
+            println!(" VERDICT: no tips-over-cap event captured yet in this window.");
+            println!("          Widest merge seen: {cap} parents (≥{cap} tips), fully merged with");
+            println!("          {all_max:.3}% orphan. To test the over-cap regime we need a spike with");
+            println!("          tips > {cap}. Keep collecting.");
+        }
+        "refuted" => {
+            println!(" VERDICT: REFUTED so far. Across {n_over} over-cap samples (excess up to");
+            println!("          {}), orphan rate averaged {over_mean:.3}% (max {over_max:.3}%).", t.max_excess);
+            println!("          Tips beyond the cap are re-merged within a block or two — GHOSTDAG");
+            println!("          absorbs the width. No measurable wasted work → the node-side");
+            println!("          mechanism is not justified by this data.");
+        }
+        "signal" => {
+            println!(" VERDICT: SIGNAL. Orphan rate rises with tip excess (corr {:+.2}); over-cap", r.excess_red_corr.unwrap_or(0.0));
+            println!("          samples average {over_mean:.2}% wasted work (max {over_max:.2}%).");
+            println!("          A real efficiency gap — the node-side parent-selection experiment");
+            println!("          is now justified by evidence.");
+        }
+        _ => {
+            println!(" VERDICT: INCONCLUSIVE. Some over-cap samples ({n_over}), orphan mean");
+            println!("          {over_mean:.2}%. Collect more data / bigger spikes to separate");
+            println!("          signal from noise.");
+        }
+    }
+    println!("{}", "═".repeat(RULE));
+}
+
+/// A band of tip width over each sample's own merge cap.
+fn band(lo: usize, hi: usize) -> String {
+    match (lo, hi) {
+        (0, 0) => "within cap".to_string(),
+        (_, usize::MAX) => format!("cap+{lo}–∞"),
+        _ => format!("cap+{lo}–{hi}"),
+    }
+}
+
+fn bar(n: usize, most: usize, width: usize) -> String {
+    if most == 0 { String::new() } else { "█".repeat((width as f64 * n as f64 / most as f64).round() as usize) }
+}
+
+fn hms(secs: f64) -> String {
+    let secs = secs as u64;
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    let mut out = String::new();
+    if h > 0 {
+        out += &format!("{h}h");
+    }
+    if m > 0 || h > 0 {
+        out += &format!("{m}m");
+    }
+    out + &format!("{s}s")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_each_sample_against_its_own_cap() {
+        // (tips, max_parents, red_rate): 12 tips is over a cap of 10 but within one of 16
+        let samples: [(usize, usize, f64); 6] =
+            [(8, 10, 0.0), (12, 10, 0.01), (12, 16, 0.0), (20, 16, 0.03), (24, 16, 0.05), (5, 10, 0.0)];
+        let path = std::env::temp_dir().join(format!("stitchbot-analyze-{}.jsonl", std::process::id()));
+        let lines: Vec<String> = samples
+            .iter()
+            .enumerate()
+            .map(|(i, &(tips, cap, red))| {
+                let rec = serde_json::json!({
+                    "t": i * 1000, "net": "test", "tips": tips, "max_parents": cap,
+                    "tip_excess": tips.saturating_sub(cap), "red_rate": red,
+                });
+                rec.to_string()
+            })
+            .collect();
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let r = analyze(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((r.samples, r.span_secs, r.tips.widest_merge), (6, 5.0, 16));
+        assert_eq!(r.tips.histogram, BTreeMap::from([(5, 1), (8, 1), (12, 2), (20, 1), (24, 1)]));
+        assert_eq!(r.tips.histogram_over_cap, BTreeMap::from([(12, 1), (20, 1), (24, 1)]));
+        let counts = |bs: &[RedBucket]| bs.iter().map(|b| (b.label.clone(), b.samples)).collect::<Vec<_>>();
+        let want = [("within cap", 3), ("cap+1–4", 2), ("cap+5–∞", 1)].map(|(l, n)| (l.to_string(), n));
+        assert_eq!(counts(&r.red_by_width), want);
+        assert_eq!(r.red_by_excess.iter().map(|b| b.samples).collect::<Vec<_>>(), [3, 1, 1, 1]);
+        assert_eq!((r.red_over_cap.samples, r.red_over_cap.red_mean), (3, Some(3.0)));
+        assert_eq!(r.verdict, "signal");
+    }
+}
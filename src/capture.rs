@@ -0,0 +1,232 @@
+//! Raw capture and replay. `Capture` wraps any source and appends each event it yields to a capture file;
+//! `ReplaySource` reads such a file back as a source. The engine takes every timestamp from the events,
+//! so replaying a capture reproduces the original snapshot stream — at real speed or accelerated.
+//!
+//! A capture is `MAGIC` followed by one frame per event: its length as a little-endian `u32`, then the
+//! event in bincode (varint integers, hashes as their 32 raw bytes). Older JSONL captures still replay.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Lines, Read, Write};
+use anyhow::{Context, Result, bail};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use tokio::time::{Duration, Instant};
+
+use crate::engine::{BlockNode, DagInfo};
+use crate::hash::Hash;
+use crate::source::{BlockSource, Link, SourceEvent};
+
+const MAGIC: &[u8] = b"STITCHCAP1\n";
+
+/// `SourceEvent` as framed: bincode can't read back the internally tagged form the JSON uses.
+#[derive(Serialize, Deserialize)]
+enum Frame {
+    Block(BlockNode, Vec<Hash>),
+    ChainChanged(Vec<Hash>, Vec<Hash>, i64),
+    Template(Vec<Hash>, i64),
+    Sync(bool, i64),
+    Dag(DagInfo),
+}
+
+impl From<&SourceEvent> for Frame {
+    fn from(ev: &SourceEvent) -> Self {
+        match ev {
+            SourceEvent::Block { node, merged } => Frame::Block(node.clone(), merged.clone()),
+            SourceEvent::ChainChanged { removed, added, t_ms } => Frame::ChainChanged(removed.clone(), added.clone(), *t_ms),
+            SourceEvent::Template { parents, t_ms } => Frame::Template(parents.clone(), *t_ms),
+            SourceEvent::Sync { synced, t_ms } => Frame::Sync(*synced, *t_ms),
+            SourceEvent::Dag(info) => Frame::Dag(info.clone()),
+        }
+    }
+}
+
+impl From<Frame> for SourceEvent {
+    fn from(f: Frame) -> Self {
+        match f {
+            Frame::Block(node, merged) => SourceEvent::Block { node, merged },
+            Frame::ChainChanged(removed, added, t_ms) => SourceEvent::ChainChanged { removed, added, t_ms },
+            Frame::Template(parents, t_ms) => SourceEvent::Template { parents, t_ms },
+            Frame::Sync(synced, t_ms) => SourceEvent::Sync { synced, t_ms },
+            Frame::Dag(info) => SourceEvent::Dag(info),
+        }
+    }
+}
+
+fn codec() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Tee: passes events through unchanged, writing each to the capture file first.
+pub struct Capture<S> {
+    inner: S,
+    out: BufWriter<File>,
+}
+
+impl<S: BlockSource> Capture<S> {
+    /// Append to the capture at `path`, starting it if the file is new or empty.
+    pub fn new(inner: S, path: &str) -> Result<Self> {
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("open capture {path}"))?;
+        let mut head = Vec::new();
+        (&mut f).take(MAGIC.len() as u64).read_to_end(&mut head)?;
+        if head.is_empty() {
+            f.write_all(MAGIC)?;
+        } else if head != MAGIC {
+            bail!("{path} is not a binary capture (an older JSONL one?): capture to a new file");
+        }
+        Ok(Self { inner, out: BufWriter::new(f) })
+    }
+}
+
+impl<S: BlockSource> BlockSource for Capture<S> {
+    fn mode(&self) -> &'static str {
+        self.inner.mode()
+    }
+
+    fn link(&self) -> Link {
+        self.inner.link()
+    }
+
+    async fn next(&mut self) -> Result<Option<SourceEvent>> {
+        let ev = self.inner.next().await?;
+        if let Some(ev) = &ev {
+            let frame = codec().serialize(&Frame::from(ev))?;
+            self.out.write_all(&(frame.len() as u32).to_le_bytes())?;
+            self.out.write_all(&frame)?;
+            // Flush once per snapshot, so a capture cut short still ends on a whole tick.
+            if matches!(ev, SourceEvent::Dag(_)) {
+                self.out.flush()?;
+            }
+        }
+        Ok(ev)
+    }
+}
+
+enum Input {
+    Frames(BufReader<File>),
+    Lines(Lines<BufReader<File>>), // an older JSONL capture
+}
+
+/// Reads a capture back, sleeping between events so they arrive `speed`× faster than recorded
+/// (`speed` ≤ 0 = no pacing, as fast as the engine goes).
+pub struct ReplaySource {
+    input: Input,
+    speed: f64,
+    start: Option<(i64, Instant)>, // (first event's t_ms, when we replayed it)
+    event: usize,
+}
+
+impl ReplaySource {
+    pub fn open(path: &str, speed: f64) -> Result<Self> {
+        let f = File::open(path).with_context(|| format!("open capture {path}"))?;
+        let mut r = BufReader::new(f);
+        let input = if r.fill_buf()?.starts_with(MAGIC) {
+            r.consume(MAGIC.len());
+            Input::Frames(r)
+        } else {
+            Input::Lines(r.lines())
+        };
+        Ok(Self { input, speed, start: None, event: 0 })
+    }
+}
+
+impl BlockSource for ReplaySource {
+    fn mode(&self) -> &'static str {
+        "replay"
+    }
+
+    async fn next(&mut self) -> Result<Option<SourceEvent>> {
+        self.event += 1;
+        let ev: SourceEvent = match &mut self.input {
+            Input::Frames(r) => {
+                let mut len = [0u8; 4];
+                match r.read_exact(&mut len) {
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                    res => res?,
+                }
+                let mut frame = vec![0; u32::from_le_bytes(len) as usize];
+                r.read_exact(&mut frame).with_context(|| format!("capture event {}: cut short", self.event))?;
+                codec().deserialize::<Frame>(&frame).with_context(|| format!("capture event {}", self.event))?.into()
+            }
+            Input::Lines(lines) => {
+                let Some(line) = lines.next() else { return Ok(None) };
+                serde_json::from_str(&line?).with_context(|| format!("capture line {}", self.event))?
+            }
+        };
+        if self.speed > 0.0 {
+            let t = ev.t_ms();
+            let (t0, i0) = *self.start.get_or_insert((t, Instant::now()));
+            let offset = (t - t0).max(0) as f64 / self.speed;
+            tokio::time::sleep_until(i0 + Duration::from_secs_f64(offset / 1000.0)).await;
+        }
+        Ok(Some(ev))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Events(std::vec::IntoIter<SourceEvent>);
+
+    impl BlockSource for Events {
+        fn mode(&self) -> &'static str {
+            "test"
+        }
+
+        async fn next(&mut self) -> Result<Option<SourceEvent>> {
+            Ok(self.0.next())
+        }
+    }
+
+    fn events() -> Vec<SourceEvent> {
+        let h = |n: u8| Hash([n; 32]);
+        let node = BlockNode {
+            hash: h(2),
+            blue_score: 7,
+            daa: 9,
+            timestamp: 1_000,
+            seen_ms: 1_200,
+            parents: vec![h(1)],
+            selected_parent: h(1),
+            is_chain: true,
+            blues: 1,
+            reds: 0,
+        };
+        vec![
+            SourceEvent::Sync { synced: true, t_ms: 1_100 },
+            SourceEvent::Block { node, merged: vec![h(1)] },
+            SourceEvent::ChainChanged { removed: Vec::new(), added: vec![h(2)], t_ms: 1_250 },
+            SourceEvent::Template { parents: vec![h(2), Hash::ZERO], t_ms: 1_280 },
+            SourceEvent::Dag(DagInfo { sink: h(2), tips: vec![h(2)], t_ms: 1_300, ..Default::default() }),
+        ]
+    }
+
+    #[tokio::test]
+    async fn replays_what_was_captured() {
+        let path = std::env::temp_dir().join(format!("stitchbot-capture-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        // captured in two runs: the second appends after the first, under the one header
+        let mut second = events();
+        let first: Vec<SourceEvent> = second.drain(..2).collect();
+        for evs in [first, second] {
+            let mut cap = Capture::new(Events(evs.into_iter()), path).unwrap();
+            while cap.next().await.unwrap().is_some() {}
+        }
+
+        let mut replay = ReplaySource::open(path, 0.0).unwrap();
+        for want in events() {
+            let got = replay.next().await.unwrap().unwrap();
+            assert_eq!(serde_json::to_value(got).unwrap(), serde_json::to_value(want).unwrap());
+        }
+        assert!(replay.next().await.unwrap().is_none());
+        let json: usize = events().iter().map(|e| serde_json::to_string(e).unwrap().len() + 1).sum();
+        assert!(std::fs::metadata(path).unwrap().len() < json as u64 / 2);
+        std::fs::remove_file(path).unwrap();
+    }
+}
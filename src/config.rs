@@ -6,31 +6,109 @@ use serde::Deserialize;
 pub struct Config {
     /// Kaspa node gRPC endpoint, host:port (scheme added in code).
     pub rpc_url: String,
+    /// More nodes to monitor alongside `rpc_url` (host:port each), compared at `/api/nodes`. The dashboard,
+    /// metrics log and capture stay on `rpc_url`.
+    #[serde(default)]
+    pub extra_nodes: Vec<String>,
     /// Port the dashboard + JSON API are served on.
     #[serde(default = "default_http_port")]
     pub http_port: u16,
-    /// How often to poll the node, milliseconds.
+    /// Block ingestion: `"poll"` (get_blocks every `poll_ms`) or `"notify"` (subscribe to block-added +
+    /// virtual-chain-changed notifications; falls back to polling if the subscription fails or dies).
+    #[serde(default = "default_ingest")]
+    pub ingest: String,
+    /// How often to poll the node, milliseconds (in notify mode: how often a snapshot is taken).
     #[serde(default = "default_poll_ms")]
     pub poll_ms: u64,
+    /// Longest wait between reconnect attempts, ms (the backoff starts at 1 s and doubles up to this).
+    #[serde(default = "default_reconnect_max_ms")]
+    pub reconnect_max_ms: u64,
+    /// A node whose virtual DAA score and sink haven't moved for this long (ms) is treated as down and
+    /// reconnected (0 = never).
+    #[serde(default = "default_stale_ms")]
+    pub stale_ms: u64,
     /// Number of recent blocks kept in the rolling DAG for metrics (bump for high BPS).
     #[serde(default = "default_window")]
     pub dag_window: usize,
     /// Max nodes sent to the dashboard canvas (kept small so the browser stays smooth at 100 BPS).
     #[serde(default = "default_viz_cap")]
     pub viz_cap: usize,
-    /// Tip-width above this flags a fracture in the UI.
+    /// Tip-width at/above this raises the fracture *proxy* (kept for comparison with the anticone test).
     #[serde(default = "default_fracture_tips")]
     pub fracture_tip_width: usize,
-    /// Blue-score spread across tips above this also flags a fracture.
+    /// Blue-score gap at/above this between mutually unordered blocks flags a fracture (and across tips,
+    /// the proxy).
     #[serde(default = "default_min_delta")]
     pub base_min_delta: u64,
+    /// "High blue score": blocks within this many blue rounds of the frontier are checked pairwise for
+    /// mutual anticone membership.
+    #[serde(default = "default_fracture_horizon")]
+    pub fracture_horizon: u64,
+    /// A mutually unordered pair older than this (ms, by header time) flags a fracture.
+    #[serde(default = "default_fracture_unordered_ms")]
+    pub fracture_unordered_ms: u64,
     /// File the JSONL metrics dataset is appended to (the measurement PoC output).
     #[serde(default = "default_log_path")]
     pub log_path: String,
+    /// File each finished fracture episode is appended to, one JSON object per line (empty = off).
+    #[serde(default = "default_fracture_log_path")]
+    pub fracture_log_path: String,
+    /// Raw capture file: every ingested event (blocks + mergesets, chain changes, DAG info) with its
+    /// receive time, replayable with `stitchbot replay <file>` (empty = off).
+    #[serde(default)]
+    pub capture_path: String,
+    /// Directory of the on-disk history behind `/api/history` (hourly partitions; empty = off).
+    #[serde(default = "default_store_path")]
+    pub store_path: String,
+    /// Days of history partitions kept.
+    #[serde(default = "default_store_days")]
+    pub store_days: u64,
+    /// Alert rules (`[[alerts]]` tables; see alerts.rs) and the webhooks they POST to (http:// only).
+    #[serde(default)]
+    pub alerts: Vec<crate::alerts::AlertRule>,
+    #[serde(default)]
+    pub alert_webhooks: Vec<String>,
     /// Merge-depth ceiling in blue-score rounds (= target_bps × 3600). Blocks not merged within this
     /// are permanently orphaned. 36000 at 10 BPS mainnet; 360000 at 100 BPS.
     #[serde(default = "default_merge_depth")]
     pub merge_depth: u64,
+    /// GHOSTDAG k values to re-colour the window with and compare against the node (empty = off). Use the
+    /// network's own k to validate, others for "what if k were different".
+    #[serde(default)]
+    pub recolour_k: Vec<u64>,
+    /// Re-colour every this many snapshots (the pass walks the whole window).
+    #[serde(default = "default_recolour_every")]
+    pub recolour_every: u64,
+    /// Block-template coverage: with a pay address set, fetch a template every `template_ms` and check
+    /// which live tips its parents reference (empty = off). Any valid address of the node's network will
+    /// do — the template is never mined.
+    #[serde(default)]
+    pub template_pay_address: String,
+    #[serde(default = "default_template_ms")]
+    pub template_ms: u64,
+    /// The network's parent cap (16 on mainnet at 10 BPS) — a template at the cap that leaves tips out is
+    /// cap-bound, one below it is leaving them out by policy.
+    #[serde(default = "default_max_block_parents")]
+    pub max_block_parents: usize,
+    /// Simulator (`stitchbot simulate`): block rate, miner count, mean one-way propagation delay and its
+    /// distribution (`fixed`, `uniform` 0..2×mean, `exp`), parent cap, GHOSTDAG k, parent policy
+    /// (`node` | `random` | `most_divergent` | `oldest_unmerged`) and RNG seed.
+    #[serde(default = "default_sim_bps")]
+    pub sim_bps: f64,
+    #[serde(default = "default_sim_miners")]
+    pub sim_miners: usize,
+    #[serde(default = "default_sim_delay_ms")]
+    pub sim_delay_ms: f64,
+    #[serde(default = "default_sim_delay_dist")]
+    pub sim_delay_dist: String,
+    #[serde(default = "default_sim_max_parents")]
+    pub sim_max_parents: usize,
+    #[serde(default = "default_sim_k")]
+    pub sim_k: u64,
+    #[serde(default = "default_sim_policy")]
+    pub sim_policy: String,
+    #[serde(default = "default_sim_seed")]
+    pub sim_seed: u64,
     /// Blue-score depth used by the confirmation-time proxy (rounds below the DAG frontier to count a
     /// block "confirmed"). Not the protocol's finality — a tunable security proxy for the correlation.
     #[serde(default = "default_conf_depth")]
@@ -39,15 +117,36 @@ pub struct Config {
 
 fn default_merge_depth() -> u64 { 36_000 }
 fn default_conf_depth() -> u64 { 120 }
+fn default_recolour_every() -> u64 { 10 }
+
+fn default_template_ms() -> u64 { 5000 }
+fn default_max_block_parents() -> usize { 16 }
+
+fn default_sim_bps() -> f64 { 10.0 }
+fn default_sim_miners() -> usize { 50 }
+fn default_sim_delay_ms() -> f64 { 500.0 }
+fn default_sim_delay_dist() -> String { "exp".to_string() }
+fn default_sim_max_parents() -> usize { 16 }
+fn default_sim_k() -> u64 { 124 }
+fn default_sim_policy() -> String { "node".to_string() }
+fn default_sim_seed() -> u64 { 1 }
 
 fn default_log_path() -> String { "stitchbot_metrics.jsonl".to_string() }
+fn default_fracture_log_path() -> String { "stitchbot_fractures.jsonl".to_string() }
+fn default_store_path() -> String { "stitchbot_history".to_string() }
+fn default_store_days() -> u64 { 14 }
 
+fn default_ingest() -> String { "poll".to_string() }
 fn default_http_port() -> u16 { 8899 }
 fn default_poll_ms() -> u64 { 1000 }
+fn default_reconnect_max_ms() -> u64 { 30_000 }
+fn default_stale_ms() -> u64 { 30_000 }
 fn default_window() -> usize { 1500 }
 fn default_viz_cap() -> usize { 600 }
 fn default_fracture_tips() -> usize { 8 }
 fn default_min_delta() -> u64 { 500 }
+fn default_fracture_horizon() -> u64 { 30 }
+fn default_fracture_unordered_ms() -> u64 { 3000 }
 
 impl Config {
     pub fn from_file(path: &str) -> anyhow::Result<Self> {
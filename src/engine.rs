@@ -1,50 +1,127 @@
 //! Rolling DAG store + health-metric snapshot builder.
 //!
-//! RPC-agnostic: `main` converts `RpcBlock` into `BlockNode`s and feeds them here; the metric logic
-//! has no dependency on the node client. Tuned for the high-BPS regime where the decisive questions are
-//! (1) how long blocks wait to be merged (merge latency vs the merge-depth ceiling) and (2) whether that
-//! lag feeds through into confirmation time.
+//! RPC-agnostic: a `BlockSource` (see `source.rs`) supplies `BlockNode`s and `DagInfo`s and `main` feeds
+//! them here; the metric logic has no dependency on the node client, and no clock of its own — every
+//! timestamp comes from the input, so replaying the same input reproduces the same snapshots. Tuned for
+//! the high-BPS regime where the decisive questions are (1) how long blocks wait to be merged (merge
+//! latency vs the merge-depth ceiling) and (2) whether that lag feeds through into confirmation time.
 
-use std::collections::{HashMap, VecDeque};
-use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use serde::{Deserialize, Serialize};
 
-const NET_DELAY_S: f64 = 0.9;
-const HISTORY: usize = 120;
-const MERGE_SAMPLES: usize = 4000;
-const CONF_SAMPLES: usize = 4000;
+use crate::alerts::AlertStatus;
+use crate::ghostdag::Ghostdag;
+use crate::hash::Hash;
+use crate::metrics::{Histogram, CONF_TIME_BUCKETS, MERGE_LAT_BUCKETS};
+use crate::sketch::{Series, Sketch, Windowed};
 
-/// Block data supplied by `main` (the RPC-facing input).
-#[derive(Clone)]
+const DELAY_PRIOR_S: f64 = 0.9; // Δ until there are enough samples to measure it
+const DELAY_MIN_SAMPLES: u64 = 30;
+const DELAY_WINDOW: usize = 0; // the `sketch::WINDOWS` entry (1m) Δ is estimated over: short, to catch spikes
+const GAP_SETTLE: u64 = 20; // blue rounds below the frontier before a block's first-child gap is taken
+const HISTORY_MS: i64 = 120_000; // span of the dashboard sparklines
+const HISTORY_STEP_MS: i64 = 1000; // at most one sparkline point per this
+const CONF_SAMPLES: usize = 4000; // (merge lag, confirmation time) pairs kept for their correlation
+const HEADLINE: usize = 1; // the `sketch::WINDOWS` entry (15m) behind the flat mean / p95 fields
+
+/// Statistics kept over every `sketch::WINDOWS` wall-clock window, as (name, unit). The first group are
+/// events, one sample each; the rest are gauges sampled once per snapshot. Nothing is sampled while the
+/// node is syncing.
+const SERIES: &[(&str, &str)] = &[
+    ("merge_lat", "rounds"),
+    ("conf_time", "seconds"),
+    ("fracture_secs", "seconds"),     // length of each fracture, when it ends
+    ("unordered_secs", "seconds"),    // how long each resolved pair stayed unordered
+    ("reorg_depth", "blocks"),        // chain blocks removed, per reorg
+    ("orphan_rounds", "rounds"),      // blue rounds below the frontier, per permanent orphan
+    ("child_gap", "seconds"),         // header time from a block to its first child
+    ("seen_lag", "seconds"),          // header time to first seen here
+    ("tip_width", "tips"),
+    ("bps", "bps"),
+    ("blue_delta", "rounds"),
+    ("red_rate", "ratio"),
+    ("stress", "index"),
+    ("unordered_pairs", "pairs"),
+    ("pending_wait", "rounds"),
+];
+const ANTICONE_CANDIDATES: usize = 64; // high-blue blocks checked pairwise per snapshot
+const FRACTURE_PAIRS_SHOWN: usize = 16;
+const FRACTURES_KEPT: usize = 32;
+const EPISODE_TIPS: usize = 64; // tip hashes remembered per fracture episode
+const CHAIN_TRACK: usize = 1024; // selected-chain blocks remembered below the sink for reorg diffs
+const REORGS_KEPT: usize = 32;
+const ORPHANS_KEPT: usize = 32;
+const COLOUR_DIFFS_SHOWN: usize = 16;
+const EXCLUDED_SHOWN: usize = 16;
+const SPARE_KEPT: usize = 256; // emptied `awaiting` lists kept for reuse
+const VIZ_DELTA_SPAN: u64 = 30; // snapshots a viz delta can reach back; older clients get the full set
+
+/// Block data supplied by a block source (the RPC-facing input).
+#[derive(Clone, Serialize, Deserialize)]
 pub struct BlockNode {
-    pub hash: String,
+    pub hash: Hash,
     pub blue_score: u64,
     pub daa: u64,
     pub timestamp: u64, // ms — block production time (confirmation baseline)
-    pub parents: Vec<String>,
+    pub seen_ms: i64,   // ms — when this monitor first received it (poll batch or push notification)
+    pub parents: Vec<Hash>,
+    pub selected_parent: Hash, // ZERO if the node didn't say (then the highest-blue parent is used)
     pub is_chain: bool,
     pub blues: u32, // mergeset blue count
     pub reds: u32,  // mergeset red count (wasted / orphaned)
 }
 
-/// Internal lifecycle wrapper — tracks when we first saw a block, when it was merged, and whether it
-/// has reached the confirmation depth (so each block contributes to the merge/confirmation stats once).
+/// Virtual-state summary that accompanies each snapshot (get_block_dag_info or a source's equivalent).
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct DagInfo {
+    pub network: String,
+    pub sink: Hash,
+    pub virtual_daa: u64,
+    pub block_count: u64,
+    pub header_count: u64,
+    pub difficulty: f64,
+    pub tips: Vec<Hash>,
+    pub t_ms: i64, // when this view was taken — the snapshot's "now"
+}
+
+/// A window slot's id: the block's ingest sequence number. Slot = id − `Engine::base`, so an id below the
+/// base is a block that has left the window, and links by id need no cleanup on eviction.
+type Id = u64;
+
+/// Internal lifecycle wrapper — tracks when we first saw a block and when it was merged (so each block
+/// contributes to the merge/confirmation stats once; `Engine::unconfirmed` holds it until it confirms).
 struct Tracked {
-    node: BlockNode,
-    merge_lag: i64, // -1 = not yet merged; else blue-score rounds it waited
-    confirmed: bool,
+    node: BlockNode,          // its `parents` emptied once linked: `parents` / `awaiting` hold them by id
+    parent_count: usize,      // as named in the header, ingested or not
+    parents: Vec<Id>,         // parents ingested so far (linked when they arrive, if after this block)
+    children: Vec<Id>,        // blocks ingested so far that have it as a parent
+    first_child: Option<u64>, // earliest header time among `children`, whatever order they arrived in
+    merged: Vec<Hash>,        // its own mergeset, kept so a later chain flip can stamp the blocks it merges
+    merge_lag: i64,      // -1 = not yet merged; else blue-score rounds it waited
+    merges_recorded: bool,
+    orphaned: bool, // counted as a permanent orphan (past merge depth while still unmerged)
+    syncing: bool,  // arrived while the node was catching up: kept in the DAG, left out of the statistics
 }
 
 pub struct Engine {
-    blocks: HashMap<String, Tracked>,
-    order: VecDeque<String>,
+    blocks: VecDeque<Tracked>, // the window in ingest order; slot i holds id base + i
+    base: Id,
+    index: HashMap<Hash, Id>,
+    awaiting: HashMap<Hash, Vec<Id>>, // parents not ingested (yet) → the blocks that named them
+    spare: Vec<Vec<Id>>,              // emptied `awaiting` lists, reused
+    sums: WindowSums,
+    by_blue: BTreeSet<(u64, Hash, Id)>, // the window by (blue score, hash): viz and fracture candidates
+    unmerged: BTreeSet<(u64, Id)>,      // not merged yet, by blue score: merge-depth expiry, longest wait
+    unconfirmed: BTreeSet<(u64, Id)>,   // merged, not yet conf_depth below the frontier, by blue score
+    gap_pending: BTreeSet<(u64, Id)>,   // first-child gap not taken yet (until GAP_SETTLE rounds down), by blue
     capacity: usize,
     viz_cap: usize,
-    tip_history: VecDeque<usize>,
-    bps_history: VecDeque<f64>,
-    red_history: VecDeque<f64>,
-    lat_history: VecDeque<f64>,
-    merge_latencies: VecDeque<u64>,
+    history: VecDeque<(i64, usize, f64, f64, f64)>, // (t_ms, tips, bps, red %, merge-lat mean), last HISTORY_MS
+    series: Vec<Windowed>, // parallel to SERIES
+    totals: Option<Vec<Sketch>>, // run-long, parallel to SERIES, once `keep_totals` is called
     max_merge_latency: u64,
+    merge_lat_hist: Histogram,
+    conf_hist: Histogram,
     conf_pairs: VecDeque<(f64, f64)>, // (merge_lag rounds, confirmation seconds)
     stress_peak: f64,
     fracture_events: u64,
@@ -52,22 +129,125 @@ pub struct Engine {
     fracture_start_ms: Option<i64>,
     last_fracture_secs: f64,
     max_fracture_secs: f64,
+    episode: Option<OpenEpisode>,
+    fracture_log: VecDeque<FractureEpisode>,
     peak_tip_width: usize,
+    open_pairs: HashSet<(Hash, Hash)>, // unordered pairs seen last snapshot (a < b)
+    resolved_pairs: u64,
+    last_unordered_secs: f64,
+    max_unordered_secs: f64,
+    chain: Vec<Hash>, // selected chain at the last snapshot, sink first
+    chain_feed: bool,   // virtual-chain-changed notifications seen: reorgs come from them, not sink diffs
+    reorgs: VecDeque<Reorg>,
+    reorg_count: u64,
+    reorg_depth_since_snapshot: usize,
+    max_reorg_depth: usize,
+    max_reorg_blue: u64,
+    frontier: u64, // highest blue score ingested so far
+    synced: bool,  // the node's last reported sync state (true until a source says otherwise)
+    orphans: VecDeque<Orphan>,
+    orphans_evicted: u64,
+    orphans_depth: u64,
+    orphans_since_snapshot: u64,
+    template: Option<(Vec<Hash>, i64)>, // block-template parents not yet compared with the tips, and when
+    template_cov: TemplateCoverage,     // the latest comparison
+    excluded_since: HashMap<Hash, (i64, u32)>, // live tip → (first template that left it out, templates since)
+    max_excluded_secs: f64,
+    viz_epoch: i64, // first snapshot's time; tells a client its sequence numbers came from another run
+    viz_seq: u64,
+    viz_prev: HashMap<String, VizNode>, // last snapshot's shown nodes, by id
+    viz_removed: VecDeque<(u64, String)>, // (snapshot that dropped it, id), last VIZ_DELTA_SPAN snapshots
+}
+
+/// Aggregates over the window, kept current as blocks arrive, change and leave, so a snapshot reads them
+/// instead of rescanning the window.
+#[derive(Default)]
+struct WindowSums {
+    parents: usize,                        // total parent count
+    parent_counts: BTreeMap<usize, usize>, // parent count → blocks with it
+    chain_blues: u64,                      // mergeset blues / reds of chain blocks
+    chain_reds: u64,
+}
+
+impl WindowSums {
+    fn add(&mut self, n: &BlockNode, parents: usize) {
+        self.parents += parents;
+        *self.parent_counts.entry(parents).or_default() += 1;
+        if n.is_chain {
+            self.chain_blues += n.blues as u64;
+            self.chain_reds += n.reds as u64;
+        }
+    }
+
+    fn remove(&mut self, n: &BlockNode, parents: usize) {
+        self.parents -= parents;
+        if let Some(c) = self.parent_counts.get_mut(&parents) {
+            *c -= 1;
+            if *c == 0 {
+                self.parent_counts.remove(&parents);
+            }
+        }
+        if n.is_chain {
+            self.chain_blues -= n.blues as u64;
+            self.chain_reds -= n.reds as u64;
+        }
+    }
+}
+
+/// The fracture in progress: what `FractureEpisode` will report once it ends.
+#[derive(Default)]
+struct OpenEpisode {
+    id: u64,
+    start_ms: i64,
+    peak_tips: usize,
+    peak_blue_delta: u64,
+    peak_blue_gap: u64,
+    tips: Vec<String>,              // short hashes, first seen first
+    pairs: HashSet<(Hash, Hash)>,   // pairs that held it open and are still in the window
+    pair_count: usize,                // distinct pairs that ever held it open
+    ended_by: Option<PairEnd>,      // latest resolution among `pairs` so far
+    reds: u32,                      // blocks produced during it that a chain block has merged as red
+}
+
+/// An unordered pair that stopped being one: the first block with both in its past.
+struct PairEnd {
+    pair: (Hash, Hash),
+    block: Hash,
+    end_ts: u64,   // header time of `block`
+    since_ts: u64, // header time of the later of the pair
+}
+
+/// One block template held against the tips of the same poll.
+#[derive(Default)]
+struct TemplateCoverage {
+    t_ms: i64,
+    parents: usize,
+    covered: usize,
+    left_out: Vec<ExcludedTip>, // longest-excluded first
+    limit: &'static str,        // "" = no template yet; "ok" = all tips referenced; else "cap" / "policy"
 }
 
 impl Engine {
     pub fn new(capacity: usize, viz_cap: usize) -> Self {
         Self {
-            blocks: HashMap::new(),
-            order: VecDeque::new(),
+            blocks: VecDeque::new(),
+            base: 0,
+            index: HashMap::new(),
+            awaiting: HashMap::new(),
+            spare: Vec::new(),
+            sums: WindowSums::default(),
+            by_blue: BTreeSet::new(),
+            unmerged: BTreeSet::new(),
+            unconfirmed: BTreeSet::new(),
+            gap_pending: BTreeSet::new(),
             capacity: capacity.max(16),
             viz_cap: viz_cap.max(50),
-            tip_history: VecDeque::new(),
-            bps_history: VecDeque::new(),
-            red_history: VecDeque::new(),
-            lat_history: VecDeque::new(),
-            merge_latencies: VecDeque::new(),
+            history: VecDeque::new(),
+            series: SERIES.iter().map(|_| Windowed::default()).collect(),
+            totals: None,
             max_merge_latency: 0,
+            merge_lat_hist: Histogram::new(MERGE_LAT_BUCKETS),
+            conf_hist: Histogram::new(CONF_TIME_BUCKETS),
             conf_pairs: VecDeque::new(),
             stress_peak: 0.0,
             fracture_events: 0,
@@ -75,81 +255,736 @@ impl Engine {
             fracture_start_ms: None,
             last_fracture_secs: 0.0,
             max_fracture_secs: 0.0,
+            episode: None,
+            fracture_log: VecDeque::new(),
             peak_tip_width: 0,
+            open_pairs: HashSet::new(),
+            resolved_pairs: 0,
+            last_unordered_secs: 0.0,
+            max_unordered_secs: 0.0,
+            chain: Vec::new(),
+            chain_feed: false,
+            reorgs: VecDeque::new(),
+            reorg_count: 0,
+            reorg_depth_since_snapshot: 0,
+            max_reorg_depth: 0,
+            max_reorg_blue: 0,
+            frontier: 0,
+            synced: true,
+            orphans: VecDeque::new(),
+            orphans_evicted: 0,
+            template: None,
+            template_cov: TemplateCoverage::default(),
+            excluded_since: HashMap::new(),
+            max_excluded_secs: 0.0,
+            viz_epoch: 0,
+            viz_seq: 0,
+            viz_prev: HashMap::new(),
+            viz_removed: VecDeque::new(),
+            orphans_depth: 0,
+            orphans_since_snapshot: 0,
         }
     }
 
-    /// Ingest a block. `merged` is its mergeset (blue+red hashes). For a new chain block we record the
-    /// merge latency of each block it merges, and stamp that block's own `merge_lag` (first merge wins).
-    pub fn ingest(&mut self, node: BlockNode, merged: &[String]) {
-        let is_new = !self.blocks.contains_key(&node.hash);
+    fn get(&self, id: Id) -> Option<&Tracked> {
+        id.checked_sub(self.base).and_then(|i| self.blocks.get(i as usize))
+    }
+
+    fn get_mut(&mut self, id: Id) -> Option<&mut Tracked> {
+        id.checked_sub(self.base).and_then(|i| self.blocks.get_mut(i as usize))
+    }
+
+    fn find(&self, h: &Hash) -> Option<&Tracked> {
+        self.index.get(h).and_then(|&id| self.get(id))
+    }
+
+    /// Window blocks with their ids, oldest first.
+    fn with_ids(&self) -> impl Iterator<Item = (Id, &Tracked)> {
+        (self.base..).zip(self.blocks.iter())
+    }
 
-        if is_new && node.is_chain {
-            for h in merged {
-                let lat = match self.blocks.get(h) {
-                    Some(t) => node.blue_score.saturating_sub(t.node.blue_score),
-                    None => continue,
-                };
-                push_bounded(&mut self.merge_latencies, lat, MERGE_SAMPLES);
-                if lat > self.max_merge_latency {
-                    self.max_merge_latency = lat;
+    /// Ingest a block. `merged` is its mergeset (blues, then reds). When a block is (or becomes) a chain
+    /// block we record the merge latency of each block it merges, and stamp that block's own `merge_lag`
+    /// (first merge wins). A re-delivered block refreshes its DAG data but keeps its first-seen time; its
+    /// header (blue score, parents) is the same, so only the window sums need redoing.
+    pub fn ingest(&mut self, mut node: BlockNode, merged: Vec<Hash>) {
+        let hash = node.hash;
+        let is_chain = node.is_chain;
+        let now_ms = node.seen_ms;
+        self.frontier = self.frontier.max(node.blue_score);
+        let id = match self.index.get(&hash).copied() {
+            Some(id) => {
+                if let Some(t) = id.checked_sub(self.base).and_then(|i| self.blocks.get_mut(i as usize)) {
+                    // refresh DAG data but preserve lifecycle stamps
+                    self.sums.remove(&t.node, t.parent_count);
+                    let seen_ms = t.node.seen_ms;
+                    t.node = node;
+                    t.node.seen_ms = seen_ms;
+                    t.node.parents = Vec::new();
+                    self.sums.add(&t.node, t.parent_count);
+                    if t.merged.is_empty() {
+                        t.merged = merged;
+                    }
+                }
+                id
+            }
+            None => {
+                let id = self.base + self.blocks.len() as Id;
+                let named = std::mem::take(&mut node.parents);
+                let mut parents = Vec::with_capacity(named.len());
+                for p in &named {
+                    match self.index.get(p).copied() {
+                        Some(pid) => {
+                            parents.push(pid);
+                            if let Some(pt) = self.get_mut(pid) {
+                                pt.children.push(id);
+                                pt.first_child = Some(pt.first_child.map_or(node.timestamp, |t| t.min(node.timestamp)));
+                            }
+                        }
+                        None => {
+                            let spare = &mut self.spare;
+                            self.awaiting.entry(*p).or_insert_with(|| spare.pop().unwrap_or_default()).push(id);
+                        }
+                    }
                 }
-                if let Some(t) = self.blocks.get_mut(h) {
-                    if t.merge_lag < 0 {
-                        t.merge_lag = lat as i64;
+                // children that arrived first
+                let children = self.awaiting.remove(&hash).unwrap_or_default();
+                let first_child = children.iter().filter_map(|&c| self.get(c)).map(|t| t.node.timestamp).min();
+                for &c in &children {
+                    if let Some(ct) = self.get_mut(c) {
+                        ct.parents.push(id);
                     }
                 }
+                if self.synced {
+                    self.observe("seen_lag", now_ms, (now_ms - node.timestamp as i64) as f64 / 1000.0);
+                    self.unmerged.insert((node.blue_score, id));
+                    self.gap_pending.insert((node.blue_score, id));
+                }
+                self.index.insert(hash, id);
+                self.sums.add(&node, named.len());
+                self.by_blue.insert((node.blue_score, hash, id));
+                self.blocks.push_back(Tracked {
+                    node,
+                    parent_count: named.len(),
+                    parents,
+                    children,
+                    first_child,
+                    merged,
+                    merge_lag: -1,
+                    merges_recorded: false,
+                    orphaned: false,
+                    syncing: !self.synced,
+                });
+                id
+            }
+        };
+        if is_chain {
+            self.record_merges(id);
+        }
+        self.take_gaps(now_ms);
+
+        // A block leaving the window without ever being merged is, as far as we can tell, orphaned.
+        while self.blocks.len() > self.capacity {
+            let Some(t) = self.blocks.pop_front() else { break };
+            let (id, blue) = (self.base, t.node.blue_score);
+            self.base += 1;
+            self.index.remove(&t.node.hash);
+            self.sums.remove(&t.node, t.parent_count);
+            self.by_blue.remove(&(blue, t.node.hash, id));
+            self.unmerged.remove(&(blue, id));
+            self.unconfirmed.remove(&(blue, id));
+            self.gap_pending.remove(&(blue, id));
+            if t.merge_lag < 0 && !t.orphaned && !t.syncing {
+                let o = Orphan::of(&t, "evicted", self.frontier, now_ms);
+                self.record_orphan(o, now_ms);
+            }
+        }
+    }
+
+    /// First-child gaps of the blocks now `GAP_SETTLE` rounds below the frontier: by then any child that
+    /// could be the earliest has arrived, whatever order they were ingested in. A child stamped before its
+    /// parent (clock skew) counts as a zero gap rather than being dropped — it still marks a fast child, and
+    /// dropping it would push the median, and so Δ, up.
+    fn take_gaps(&mut self, now_ms: i64) {
+        while let Some(&(blue, id)) = self.gap_pending.first()
+            && blue + GAP_SETTLE < self.frontier
+        {
+            self.gap_pending.pop_first();
+            let gap = self.get(id).and_then(|t| Some(t.first_child?.saturating_sub(t.node.timestamp)));
+            if let Some(g) = gap {
+                self.observe("child_gap", now_ms, g as f64 / 1000.0);
             }
         }
+    }
 
-        if is_new {
-            self.order.push_back(node.hash.clone());
-            self.blocks.insert(node.hash.clone(), Tracked { node, merge_lag: -1, confirmed: false });
-        } else if let Some(t) = self.blocks.get_mut(&node.hash) {
-            // refresh DAG data but preserve lifecycle stamps
-            t.node = node;
+    /// Effective propagation delay Δ, measured. A block's first child comes from the first miner to find a
+    /// block after the parent reached it, so the header-time gap is Δ plus an exponential wait at rate λ,
+    /// whose median is ln 2 / λ: Δ = median gap − ln 2 / λ. The bounds are the order statistics that
+    /// bracket the median at ~95% (ranks n/2 ± 0.98√n), shifted the same way. Tip width gives a
+    /// cross-check: a block stays a tip for Δ + 1/λ, so by Little's law the mean tip count is λΔ + 1.
+    /// All three are read from the `DELAY_WINDOW` window of the snapshot's `stats`.
+    fn estimate_delay(stats: &[Series], bps: f64) -> DelayEstimate {
+        let window = |name: &str| {
+            let series = stats.iter().find(|s| s.name == name);
+            series.and_then(|s| s.windows.get(DELAY_WINDOW)).cloned().unwrap_or_default()
+        };
+        let (gaps, tips, lag) = (window("child_gap"), window("tip_width"), window("seen_lag"));
+        let mut d = DelayEstimate {
+            delay_secs: DELAY_PRIOR_S,
+            delay_lo_secs: DELAY_PRIOR_S,
+            delay_hi_secs: DELAY_PRIOR_S,
+            delay_samples: gaps.count,
+            delay_source: "prior",
+            delay_tips_secs: if bps > 0.0 { round4((tips.mean - 1.0).max(0.0) / bps) } else { 0.0 },
+            delay_seen_secs: lag.p50,
+        };
+        if bps <= 0.0 || gaps.count < DELAY_MIN_SAMPLES {
+            return d;
         }
+        let shift = std::f64::consts::LN_2 / bps;
+        let half = 0.98 / (gaps.count as f64).sqrt();
+        let at = |q: f64| round4((gaps.sketch.quantile(q.clamp(0.0, 1.0)) - shift).max(0.0));
+        (d.delay_secs, d.delay_lo_secs, d.delay_hi_secs) = (at(0.5), at(0.5 - half), at(0.5 + half));
+        d.delay_source = "child_gap";
+        d
+    }
 
-        while self.order.len() > self.capacity {
-            if let Some(old) = self.order.pop_front() {
-                self.blocks.remove(&old);
+    /// One sample of a `SERIES` statistic.
+    fn observe(&mut self, name: &str, t_ms: i64, v: f64) {
+        if let Some(i) = SERIES.iter().position(|&(n, _)| n == name) {
+            self.series[i].observe(t_ms, v);
+            if let Some(t) = &mut self.totals {
+                t[i].observe(v);
             }
         }
     }
 
+    /// Every `SERIES` statistic's per-minute sketches for minutes `[from, to)`, for the store.
+    pub fn sketch_minutes(&self, from: i64, to: i64) -> impl Iterator<Item = (&'static str, i64, &Sketch)> {
+        SERIES.iter().zip(&self.series).flat_map(move |(&(name, _), w)| w.minutes(from, to).map(move |(m, s)| (name, m, s)))
+    }
+
+    /// Merge one saved minute of `name` back in (see `Store::load_sketches`).
+    pub fn restore_sketch(&mut self, name: &str, minute: i64, s: &Sketch) {
+        if let Some(i) = SERIES.iter().position(|&(n, _)| n == name) {
+            self.series[i].restore(minute, s);
+        }
+    }
+
+    /// From now on, also fold every `SERIES` sample into one sketch per statistic for the whole run
+    /// (the experiment harness, which wants distributions rather than windows).
+    pub fn keep_totals(&mut self) {
+        self.totals.get_or_insert_with(|| SERIES.iter().map(|_| Sketch::default()).collect());
+    }
+
+    /// Everything `name` saw since `keep_totals`; empty if it was never called.
+    pub fn total(&self, name: &str) -> Sketch {
+        let i = SERIES.iter().position(|&(n, _)| n == name);
+        self.totals.as_ref().zip(i).map(|(t, i)| t[i].clone()).unwrap_or_default()
+    }
+
+    fn record_orphan(&mut self, o: Orphan, now_ms: i64) {
+        match o.reason {
+            "evicted" => self.orphans_evicted += 1,
+            _ => self.orphans_depth += 1,
+        }
+        self.orphans_since_snapshot += 1;
+        self.observe("orphan_rounds", now_ms, o.rounds as f64);
+        push_bounded(&mut self.orphans, o, ORPHANS_KEPT);
+    }
+
+    /// The node's sync state changed. While it is catching up (IBD, or a burst after a restart) blocks
+    /// arrive far faster and later than the network made them: they still build the DAG but are tagged so
+    /// merge latency, confirmation time and orphans ignore them, and no fracture is flagged.
+    pub fn sync_state(&mut self, synced: bool) {
+        self.synced = synced;
+    }
+
+    /// Apply a virtual-chain change (push mode): blocks that left the selected chain lose their chain
+    /// flag, blocks that joined it gain it and have their mergesets' merge latencies recorded.
+    pub fn chain_changed(&mut self, removed: &[Hash], added: &[Hash], t_ms: i64) {
+        self.chain_feed = true;
+        if !removed.is_empty() {
+            self.record_reorg(removed.to_vec(), added.to_vec(), t_ms);
+        }
+        for h in removed {
+            if let Some(&id) = self.index.get(h) {
+                self.set_chain(id, false);
+            }
+        }
+        for h in added {
+            if let Some(&id) = self.index.get(h) {
+                self.set_chain(id, true);
+                self.record_merges(id);
+            }
+        }
+    }
+
+    fn set_chain(&mut self, id: Id, is_chain: bool) {
+        let Some(t) = id.checked_sub(self.base).and_then(|i| self.blocks.get_mut(i as usize)) else { return };
+        if t.node.is_chain != is_chain {
+            self.sums.remove(&t.node, t.parent_count);
+            t.node.is_chain = is_chain;
+            self.sums.add(&t.node, t.parent_count);
+        }
+    }
+
+    /// Record the merge latency of every block in `id`'s mergeset — once per chain block.
+    fn record_merges(&mut self, id: Id) {
+        let (blue_score, t_ms, blues, merged) = match self.get_mut(id) {
+            Some(t) if !t.merges_recorded => {
+                t.merges_recorded = true;
+                (t.node.blue_score, t.node.seen_ms, t.node.blues as usize, std::mem::take(&mut t.merged))
+            }
+            _ => return,
+        };
+        for (i, h) in merged.iter().enumerate() {
+            let Some(&mid) = self.index.get(h) else { continue };
+            let Some(t) = self.get_mut(mid) else { continue };
+            let (lat, blue, ts) = (blue_score.saturating_sub(t.node.blue_score), t.node.blue_score, t.node.timestamp);
+            let first = t.merge_lag < 0;
+            if first {
+                t.merge_lag = lat as i64;
+            }
+            if t.syncing {
+                continue;
+            }
+            if first {
+                self.unmerged.remove(&(blue, mid));
+                self.unconfirmed.insert((blue, mid));
+                self.merge_lat_hist.observe(lat as f64); // once per block, even if a reorg re-merges it
+                // the mergeset lists its blues first
+                if i >= blues
+                    && let Some(ep) = self.episode.as_mut()
+                    && ts as i64 >= ep.start_ms
+                {
+                    ep.reds += 1;
+                }
+            }
+            self.observe("merge_lat", t_ms, lat as f64);
+            if lat > self.max_merge_latency {
+                self.max_merge_latency = lat;
+            }
+        }
+        if let Some(t) = self.get_mut(id) {
+            t.merged = merged;
+        }
+    }
+
+    /// Selected-parent chain from `sink` down through the window (at most `CHAIN_TRACK` blocks).
+    fn selected_chain(&self, sink: &Hash) -> Vec<Hash> {
+        let mut chain = Vec::new();
+        let mut cur = self.find(sink);
+        while let Some(t) = cur {
+            chain.push(t.node.hash);
+            if chain.len() >= CHAIN_TRACK {
+                break;
+            }
+            cur = self.selected_parent(t);
+        }
+        chain
+    }
+
+    /// Selected chain as of the last snapshot, sink first.
+    pub fn chain(&self) -> &[Hash] {
+        &self.chain
+    }
+
+    /// The node's selected parent when it told us, else the highest-blue in-window parent (chain blocks
+    /// first on ties).
+    fn selected_parent(&self, t: &Tracked) -> Option<&Tracked> {
+        if !t.node.selected_parent.is_zero() {
+            return self.find(&t.node.selected_parent);
+        }
+        t.parents
+            .iter()
+            .filter_map(|&p| self.get(p))
+            .max_by(|a, b| {
+                (a.node.blue_score, a.node.is_chain, &b.node.hash).cmp(&(b.node.blue_score, b.node.is_chain, &a.node.hash))
+            })
+    }
+
+    /// Poll mode: diff the selected chain under the new sink against the previous one. If the old sink is
+    /// still on the chain the chain was merely extended; otherwise everything above the fork point — the
+    /// first block of the new chain that the old one holds too — was reorganized away. A walk that ends
+    /// (at `CHAIN_TRACK` or a window gap) before reaching the old chain is a discontinuity, not a reorg:
+    /// after an outage or a catch-up burst the chain is simply resynced.
+    fn track_chain(&mut self, sink: &Hash, now_ms: i64) {
+        if !self.index.contains_key(sink) {
+            return;
+        }
+        let chain = self.selected_chain(sink);
+        if !self.chain_feed && !self.chain.is_empty() && self.chain[0] != *sink && !chain.contains(&self.chain[0]) {
+            let on_old: HashSet<&Hash> = self.chain.iter().collect();
+            if let Some(i) = chain.iter().position(|h| on_old.contains(h)) {
+                let fork = chain[i];
+                let removed: Vec<Hash> = self.chain.iter().take_while(|&&h| h != fork).copied().collect();
+                let mut added = chain[..i].to_vec();
+                added.reverse();
+                self.record_reorg(removed, added, now_ms);
+            }
+        }
+        self.chain = chain;
+    }
+
+    /// Record one reorg. `removed` / `added` are the chain blocks that left / joined the selected chain;
+    /// depth is measured in removed blocks and in blue score from the old sink down to the fork point.
+    /// Chain churn while the node is syncing is not counted at all.
+    fn record_reorg(&mut self, mut removed: Vec<Hash>, mut added: Vec<Hash>, now_ms: i64) {
+        if !self.synced {
+            return;
+        }
+        let blue = |h: &Hash| self.find(h).map(|t| t.node.blue_score);
+        removed.sort_by_key(|h| std::cmp::Reverse(blue(h)));
+        added.sort_by_key(|h| blue(h));
+        let old_top = removed.first().and_then(blue);
+        let fork = removed.last().and_then(|h| self.find(h)).and_then(|t| self.selected_parent(t));
+        let blue_depth = match (old_top, fork) {
+            (Some(top), Some(f)) => top.saturating_sub(f.node.blue_score),
+            (Some(top), None) => top.saturating_sub(removed.last().and_then(blue).unwrap_or(top)) + 1,
+            _ => 0,
+        };
+        let r = Reorg {
+            t_ms: now_ms,
+            depth: removed.len(),
+            blue_depth,
+            old_sink: removed.first().map(Hash::short).unwrap_or_default(),
+            new_sink: added.last().map(Hash::short).unwrap_or_default(),
+            fork: fork.map(|t| t.node.hash.short()).unwrap_or_default(),
+            removed: removed.iter().map(Hash::short).collect(),
+            added: added.iter().map(Hash::short).collect(),
+        };
+        self.reorg_count += 1;
+        self.observe("reorg_depth", now_ms, r.depth as f64);
+        self.reorg_depth_since_snapshot = self.reorg_depth_since_snapshot.max(r.depth);
+        self.max_reorg_depth = self.max_reorg_depth.max(r.depth);
+        self.max_reorg_blue = self.max_reorg_blue.max(r.blue_depth);
+        push_bounded(&mut self.reorgs, r, REORGS_KEPT);
+    }
+
+    /// `id` plus every window block that has it in its past (walking child links).
+    fn closed_future(&self, id: Id) -> HashSet<Id> {
+        let mut seen: HashSet<Id> = HashSet::from([id]);
+        let mut stack = vec![id];
+        while let Some(x) = stack.pop() {
+            for &c in self.get(x).map(|t| t.children.as_slice()).unwrap_or_default() {
+                if self.get(c).is_some() && seen.insert(c) {
+                    stack.push(c);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Anticone scan over the high-blue frontier (blocks within `horizon` blue rounds of it). Two blocks
+    /// are *unordered* while no block in the window has both in its closed past — i.e. their closed
+    /// futures are disjoint. A pair that was unordered last snapshot and no longer is was resolved by the
+    /// earliest block (header time) in the intersection of those futures; that interval is recorded as
+    /// how long it stayed unordered. Returns the currently unordered pairs, oldest first.
+    fn scan_anticone(&mut self, frontier: u64, horizon: u64, now_ms: i64) -> (Vec<FracturePair>, Vec<PairEnd>) {
+        let mut cands: Vec<(Id, &Tracked)> = self
+            .by_blue
+            .range((frontier.saturating_sub(horizon), Hash::ZERO, 0)..)
+            .filter_map(|&(_, _, id)| Some((id, self.get(id)?)))
+            .collect();
+        cands.sort_by(|(_, a), (_, b)| b.node.blue_score.cmp(&a.node.blue_score).then_with(|| a.node.hash.cmp(&b.node.hash)));
+        cands.truncate(ANTICONE_CANDIDATES);
+
+        // closed futures of the candidates, plus of last snapshot's open pairs so we can see how they resolved
+        let mut futures: HashMap<Id, HashSet<Id>> = HashMap::new();
+        let watched = self.open_pairs.iter().flat_map(|(a, b)| [a, b]).filter_map(|h| self.index.get(h).copied());
+        for id in cands.iter().map(|&(id, _)| id).chain(watched) {
+            futures.entry(id).or_insert_with(|| self.closed_future(id));
+        }
+
+        let mut open: Vec<FracturePair> = Vec::new();
+        for (i, &(ia, a)) in cands.iter().enumerate() {
+            for &(ib, b) in &cands[i + 1..] {
+                if !futures[&ia].is_disjoint(&futures[&ib]) {
+                    continue;
+                }
+                let since = a.node.timestamp.max(b.node.timestamp) as i64;
+                let (x, y) = if a.node.hash < b.node.hash { (a, b) } else { (b, a) };
+                open.push(FracturePair {
+                    a: x.node.hash.short(),
+                    b: y.node.hash.short(),
+                    blue_gap: x.node.blue_score.abs_diff(y.node.blue_score),
+                    unordered_secs: round2((now_ms - since).max(0) as f64 / 1000.0),
+                    key: (x.node.hash, y.node.hash),
+                });
+            }
+        }
+        let now_open: HashSet<(Hash, Hash)> = open.iter().map(|p| p.key).collect();
+
+        let mut gone: Vec<&(Hash, Hash)> = self.open_pairs.difference(&now_open).collect();
+        gone.sort();
+        let mut ends: Vec<PairEnd> = Vec::new();
+        for &(a, b) in gone {
+            let (Some(&ia), Some(&ib)) = (self.index.get(&a), self.index.get(&b)) else { continue };
+            let (Some(fa), Some(fb)) = (futures.get(&ia), futures.get(&ib)) else { continue };
+            let Some(end) = fa.intersection(fb).filter_map(|&id| self.get(id)).min_by_key(|t| (t.node.timestamp, t.node.hash))
+            else {
+                continue; // left the candidate set without being merged yet (still unordered, just lower now)
+            };
+            let ts = |id| self.get(id).map(|t| t.node.timestamp).unwrap_or(0);
+            ends.push(PairEnd { pair: (a, b), block: end.node.hash, end_ts: end.node.timestamp, since_ts: ts(ia).max(ts(ib)) });
+        }
+
+        for secs in ends.iter().map(|e| e.end_ts.saturating_sub(e.since_ts) as f64 / 1000.0) {
+            self.resolved_pairs += 1;
+            if self.synced {
+                self.observe("unordered_secs", now_ms, secs);
+            }
+            self.last_unordered_secs = secs;
+            if secs > self.max_unordered_secs {
+                self.max_unordered_secs = secs;
+            }
+        }
+        self.open_pairs = now_open;
+        open.sort_by(|a, b| b.unordered_secs.total_cmp(&a.unordered_secs).then(b.blue_gap.cmp(&a.blue_gap)));
+        (open, ends)
+    }
+
+    /// Carry the open fracture episode through one snapshot: absorb pair resolutions, then either grow it
+    /// (still fractured) or close it into the log. Returns the episode that ended here, if any.
+    fn track_episode(&mut self, fractured: &[&FracturePair], ends: Vec<PairEnd>, tips: &[Hash], blue_delta: u64, now_ms: i64) -> Option<FractureEpisode> {
+        if let Some(ep) = self.episode.as_mut() {
+            for e in ends.into_iter().filter(|e| ep.pairs.contains(&e.pair)) {
+                if ep.ended_by.as_ref().is_none_or(|b| (e.end_ts, &e.block) > (b.end_ts, &b.block)) {
+                    ep.ended_by = Some(e);
+                }
+            }
+        }
+        if !fractured.is_empty() {
+            let id = self.fracture_events;
+            let ep = self.episode.get_or_insert_with(|| OpenEpisode { id, start_ms: now_ms, ..Default::default() });
+            ep.peak_tips = ep.peak_tips.max(tips.len());
+            ep.peak_blue_delta = ep.peak_blue_delta.max(blue_delta);
+            ep.peak_blue_gap = ep.peak_blue_gap.max(fractured.iter().map(|p| p.blue_gap).max().unwrap_or(0));
+            for t in tips.iter().map(Hash::short) {
+                if ep.tips.len() < EPISODE_TIPS && !ep.tips.contains(&t) {
+                    ep.tips.push(t);
+                }
+            }
+            for p in fractured {
+                if ep.pairs.insert(p.key) {
+                    ep.pair_count += 1;
+                }
+            }
+            // a pair whose blocks left the window can no longer be seen to resolve
+            ep.pairs.retain(|(a, b)| self.index.contains_key(a) && self.index.contains_key(b));
+            return None;
+        }
+        let ep = self.episode.take()?;
+        let closed = self.episode_report(&ep, now_ms, true);
+        push_bounded(&mut self.fracture_log, closed.clone(), FRACTURES_KEPT);
+        Some(closed)
+    }
+
+    /// `ep` as of `now_ms` — closed there if `ended`, else still running.
+    fn episode_report(&self, ep: &OpenEpisode, now_ms: i64, ended: bool) -> FractureEpisode {
+        let until = now_ms;
+        let by = ep.ended_by.as_ref();
+        FractureEpisode {
+            id: ep.id,
+            start_ms: ep.start_ms,
+            end_ms: ended.then_some(now_ms),
+            secs: round2((until - ep.start_ms) as f64 / 1000.0),
+            peak_tip_width: ep.peak_tips,
+            peak_blue_delta: ep.peak_blue_delta,
+            peak_blue_gap: ep.peak_blue_gap,
+            pairs: ep.pair_count,
+            tips: ep.tips.clone(),
+            ended_by: by.map(|e| e.block.short()),
+            ended_by_parents: by.and_then(|e| self.find(&e.block)).map(|t| t.parent_count).unwrap_or(0),
+            resolve_secs: by.map(|e| round2(e.end_ts.saturating_sub(e.since_ts) as f64 / 1000.0)),
+            reds: ep.reds,
+        }
+    }
+
+    /// Window blocks in topological order (parents before children), ingest order otherwise.
+    fn topo_order(&self) -> Vec<&Tracked> {
+        let mut out: Vec<&Tracked> = Vec::with_capacity(self.blocks.len());
+        let mut done = vec![false; self.blocks.len()];
+        let slot = |id: Id| (id - self.base) as usize;
+        for (id, t) in self.with_ids() {
+            let mut stack: Vec<(Id, &Tracked, bool)> = vec![(id, t, false)];
+            while let Some((id, t, expanded)) = stack.pop() {
+                if done[slot(id)] {
+                    continue;
+                }
+                if expanded {
+                    done[slot(id)] = true;
+                    out.push(t);
+                    continue;
+                }
+                stack.push((id, t, true));
+                for &p in &t.parents {
+                    if let Some(pt) = self.get(p)
+                        && !done[slot(p)]
+                    {
+                        stack.push((p, pt, false));
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Re-run GHOSTDAG over the window for each `k` and compare with the node's verdicts. Only blocks
+    /// whose colouring stayed clear of the window edge (and whose node mergeset we hold) are checked; a
+    /// disagreement is any difference in the mergeset blue set or in the blue-score increment.
+    pub fn recolour(&self, ks: &[u64]) -> Vec<RecolourReport> {
+        let topo = self.topo_order();
+        ks.iter()
+            .map(|&k| {
+                let mut gd = Ghostdag::new(k);
+                let mut rep = RecolourReport { k, ..Default::default() };
+                let (mut local_blues, mut local_reds, mut node_blues, mut node_reds) = (0u64, 0u64, 0u64, 0u64);
+                for t in &topo {
+                    let n = &t.node;
+                    let held: Vec<Hash> = t.parents.iter().filter_map(|&p| self.get(p)).map(|p| p.node.hash).collect();
+                    let d = gd.add(n.hash, &held, t.parent_count - held.len(), n.blue_score);
+                    let nb = n.blues as usize;
+                    if d.truncated || t.merged.len() != nb + n.reds as usize {
+                        continue;
+                    }
+                    rep.checked += 1;
+                    if n.is_chain {
+                        local_blues += d.blues.len() as u64;
+                        local_reds += d.reds.len() as u64;
+                        node_blues += n.blues as u64;
+                        node_reds += n.reds as u64;
+                    }
+                    let node_set: HashSet<&Hash> = t.merged[..nb].iter().collect();
+                    let local_set: HashSet<&Hash> = d.blues.iter().collect();
+                    if node_set == local_set {
+                        continue;
+                    }
+                    rep.disagreements += 1;
+                    if rep.samples.len() < COLOUR_DIFFS_SHOWN {
+                        rep.samples.push(ColourDiff {
+                            hash: n.hash.short(),
+                            blue_score: n.blue_score,
+                            score_diff: d.blues.len() as i64 - nb as i64,
+                            local_only_blue: local_set.difference(&node_set).map(|h| h.short()).collect(),
+                            node_only_blue: node_set.difference(&local_set).map(|h| h.short()).collect(),
+                        });
+                    }
+                }
+                rep.red_rate = round4(ratio(local_reds, local_blues + local_reds));
+                rep.node_red_rate = round4(ratio(node_reds, node_blues + node_reds));
+                rep
+            })
+            .collect()
+    }
+
+    /// Parents of a block template fetched at `t_ms`; compared with the tips at the next snapshot.
+    pub fn template(&mut self, parents: Vec<Hash>, t_ms: i64) {
+        self.template = Some((parents, t_ms));
+    }
+
+    /// Which live tips the template references, how long each left-out tip has been passed over, and
+    /// whether the exclusions are forced by the parent cap or chosen (the template had room to spare).
+    fn compare_template(&mut self, parents: &[Hash], tips: &[Hash], t_ms: i64, max_block_parents: usize) {
+        let referenced: HashSet<&Hash> = parents.iter().collect();
+        let live: HashSet<&Hash> = tips.iter().collect();
+        // a streak ends when the tip is referenced or stops being a tip (merged by someone else's block)
+        self.excluded_since.retain(|h, _| live.contains(h) && !referenced.contains(h));
+        let mut left_out = Vec::new();
+        for h in tips.iter().filter(|h| !referenced.contains(h)) {
+            let blue_score = self.find(h).map(|t| t.node.blue_score).unwrap_or(0);
+            let e = self.excluded_since.entry(*h).or_insert((t_ms, 0));
+            e.1 += 1;
+            let secs = (t_ms - e.0) as f64 / 1000.0;
+            self.max_excluded_secs = self.max_excluded_secs.max(secs);
+            left_out.push(ExcludedTip {
+                hash: h.short(),
+                blue_score,
+                excluded_secs: round2(secs),
+                templates: e.1,
+            });
+        }
+        left_out.sort_by(|a, b| b.excluded_secs.total_cmp(&a.excluded_secs).then(a.blue_score.cmp(&b.blue_score)));
+        let limit = match () {
+            _ if left_out.is_empty() => "ok",
+            _ if parents.len() >= max_block_parents => "cap",
+            _ => "policy",
+        };
+        self.template_cov = TemplateCoverage {
+            t_ms,
+            parents: parents.len(),
+            covered: tips.len() - left_out.len(),
+            left_out,
+            limit,
+        };
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn snapshot(
         &mut self,
-        network: String,
-        sink: String,
-        virtual_daa: u64,
-        block_count: u64,
-        header_count: u64,
-        difficulty: f64,
+        info: &DagInfo,
         bps: f64,
-        tips: &[String],
         fracture_tip_width: usize,
         min_delta: u64,
+        fracture_horizon: u64,
+        fracture_unordered_ms: u64,
         merge_depth: u64,
         conf_depth: u64,
+        max_block_parents: usize,
     ) -> Snapshot {
-        let now_ms = chrono::Utc::now().timestamp_millis();
+        let now_ms = info.t_ms;
+        let tips = &info.tips;
         let tip_width = tips.len();
         if tip_width > self.peak_tip_width {
             self.peak_tip_width = tip_width;
         }
 
-        let frontier = self.blocks.values().map(|t| t.node.blue_score).max().unwrap_or(0);
+        let frontier = self.frontier;
+        // parents that never arrived (older than the window) stop waiting once their children are evicted
+        let (base, spare) = (self.base, &mut self.spare);
+        self.awaiting.retain(|_, cs| {
+            cs.retain(|&c| c >= base);
+            if cs.is_empty() && spare.len() < SPARE_KEPT {
+                spare.push(std::mem::take(cs));
+            }
+            !cs.is_empty()
+        });
+        self.track_chain(&info.sink, now_ms);
+        if let Some((parents, t)) = self.template.take() {
+            self.compare_template(&parents, tips, t, max_block_parents);
+        }
+        let reorg_depth = std::mem::take(&mut self.reorg_depth_since_snapshot);
 
         // Harvest confirmations: blocks now `conf_depth` below the frontier record (merge_lag, secs).
+        // (taken in (blue score, id) order, so a replay reproduces the same sample queue)
         let mut fresh: Vec<(f64, f64)> = Vec::new();
-        for t in self.blocks.values_mut() {
-            if !t.confirmed && t.merge_lag >= 0 && frontier.saturating_sub(t.node.blue_score) >= conf_depth {
-                // baseline = block production time (not first-observed) to avoid observation-time bias
-                fresh.push((t.merge_lag as f64, (now_ms - t.node.timestamp as i64) as f64 / 1000.0));
-                t.confirmed = true;
-            }
+        while let Some(&(blue, id)) = self.unconfirmed.first()
+            && frontier.saturating_sub(blue) >= conf_depth
+        {
+            self.unconfirmed.pop_first();
+            let Some(t) = self.get(id) else { continue };
+            // baseline = block production time (not first-observed) to avoid observation-time bias
+            let (lag, secs) = (t.merge_lag as f64, (now_ms - t.node.timestamp as i64) as f64 / 1000.0);
+            self.conf_hist.observe(secs);
+            self.observe("conf_time", now_ms, secs);
+            fresh.push((lag, secs));
+        }
+        // Past the merge-depth ceiling and still unmerged: permanently orphaned, whatever happens next.
+        // longest wait of a block not merged (yet), counting the ones about to expire
+        let pending_max = self.unmerged.first().map(|&(blue, _)| frontier.saturating_sub(blue)).unwrap_or(0);
+        while merge_depth > 0
+            && let Some(&(blue, id)) = self.unmerged.first()
+            && frontier.saturating_sub(blue) > merge_depth
+        {
+            self.unmerged.pop_first();
+            let Some(t) = self.get_mut(id) else { continue };
+            t.orphaned = true;
+            let o = Orphan::of(t, "merge_depth", frontier, now_ms);
+            self.record_orphan(o, now_ms);
         }
+        let orphans_new = std::mem::take(&mut self.orphans_since_snapshot);
+
         for p in fresh {
             self.conf_pairs.push_back(p);
             while self.conf_pairs.len() > CONF_SAMPLES {
@@ -158,42 +993,40 @@ impl Engine {
         }
 
         let tip_blues: Vec<u64> =
-            tips.iter().filter_map(|t| self.blocks.get(t)).map(|t| t.node.blue_score).collect();
+            tips.iter().filter_map(|t| self.find(t)).map(|t| t.node.blue_score).collect();
         let blue_min = tip_blues.iter().copied().min().unwrap_or(0);
         let blue_max = tip_blues.iter().copied().max().unwrap_or(0);
         let blue_delta = blue_max.saturating_sub(blue_min);
 
-        let pcounts: Vec<usize> = self.blocks.values().map(|t| t.node.parents.len()).collect();
-        let max_parents = pcounts.iter().copied().max().unwrap_or(0);
-        let avg_parents =
-            if pcounts.is_empty() { 0.0 } else { pcounts.iter().sum::<usize>() as f64 / pcounts.len() as f64 };
+        let max_parents = self.sums.parent_counts.last_key_value().map(|(&n, _)| n).unwrap_or(0);
+        let avg_parents = ratio(self.sums.parents as u64, self.blocks.len() as u64);
         let tip_excess = tip_width.saturating_sub(max_parents.max(1));
 
-        let (mut blues_w, mut reds_w) = (0u64, 0u64);
-        for t in self.blocks.values() {
-            if t.node.is_chain {
-                blues_w += t.node.blues as u64;
-                reds_w += t.node.reds as u64;
-            }
-        }
+        let (blues_w, reds_w) = (self.sums.chain_blues, self.sums.chain_reds);
         let red_rate = if blues_w + reds_w > 0 { reds_w as f64 / (blues_w + reds_w) as f64 } else { 0.0 };
 
-        let (lat_mean, lat_p95, lat_max) = latency_stats(&self.merge_latencies, self.max_merge_latency);
-        // headroom to the merge-depth cliff: how much of the budget the worst merge used.
-        let depth_used_pct = if merge_depth > 0 { 100.0 * lat_max as f64 / merge_depth as f64 } else { 0.0 };
+        let lat_max = self.max_merge_latency;
+        // headroom to the merge-depth cliff: how much of the budget the worst wait used — merged blocks and
+        // blocks still waiting alike (counting only completed merges would hide exactly the ones at risk).
+        let worst_wait = lat_max.max(pending_max);
+        let depth_used_pct = if merge_depth > 0 { 100.0 * worst_wait as f64 / merge_depth as f64 } else { 0.0 };
 
         // confirmation-time proxy + its correlation with merge lag.
-        let conf_secs: Vec<f64> = self.conf_pairs.iter().map(|&(_, s)| s).collect();
-        let conf_time_mean = mean(&conf_secs);
-        let conf_time_p95 = p95(&conf_secs);
         let conf_corr = correlation(&self.conf_pairs);
 
-        let stress = bps * bps * NET_DELAY_S * (tip_width.max(1) as f64);
-        if stress > self.stress_peak && self.blocks.len() > 24 {
-            self.stress_peak = stress;
-        }
-
-        let fracture = tip_width >= fracture_tip_width || blue_delta >= min_delta;
+        // A fracture is what the README defines: high-blue blocks mutually in each other's anticone, here
+        // unordered for at least `fracture_unordered_ms` or apart by `min_delta` blue score. The old
+        // tip-width / tip-spread proxy is kept alongside for comparison.
+        let (pairs, ends) = self.scan_anticone(frontier, fracture_horizon, now_ms);
+        let fractured: Vec<&FracturePair> = pairs
+            .iter()
+            .filter(|p| self.synced && (p.unordered_secs * 1000.0 >= fracture_unordered_ms as f64 || p.blue_gap >= min_delta))
+            .collect();
+        let fracture = !fractured.is_empty();
+        let fracture_proxy = self.synced && (tip_width >= fracture_tip_width || blue_delta >= min_delta);
+        let unordered_pairs = pairs.len();
+        let unordered_oldest_secs = pairs.first().map(|p| p.unordered_secs).unwrap_or(0.0);
+        let unordered_max_gap = pairs.iter().map(|p| p.blue_gap).max().unwrap_or(0);
         if fracture {
             if !self.was_fractured {
                 self.fracture_events += 1;
@@ -206,120 +1039,240 @@ impl Engine {
             if self.last_fracture_secs > self.max_fracture_secs {
                 self.max_fracture_secs = self.last_fracture_secs;
             }
+            self.observe("fracture_secs", now_ms, self.last_fracture_secs);
         }
         self.was_fractured = fracture;
+        let fracture_ended = self.track_episode(&fractured, ends, tips, blue_delta, now_ms);
+        let fracture_open = self.episode.as_ref().map(|ep| self.episode_report(ep, now_ms, false));
         let fracture_secs = self.fracture_start_ms.map(|s| (now_ms - s) as f64 / 1000.0).unwrap_or(0.0);
 
-        push_bounded(&mut self.tip_history, tip_width, HISTORY);
-        push_bounded(&mut self.bps_history, bps, HISTORY);
-        push_bounded(&mut self.red_history, red_rate * 100.0, HISTORY);
-        push_bounded(&mut self.lat_history, lat_mean, HISTORY);
+        if self.synced {
+            let gauges = [
+                ("tip_width", tip_width as f64),
+                ("bps", bps),
+                ("blue_delta", blue_delta as f64),
+                ("red_rate", red_rate),
+                ("unordered_pairs", unordered_pairs as f64),
+                ("pending_wait", pending_max as f64),
+            ];
+            for (name, v) in gauges {
+                self.observe(name, now_ms, v);
+            }
+        }
+        let mut stats: Vec<Series> =
+            SERIES.iter().zip(&mut self.series).map(|(&(name, unit), w)| Series { name, unit, windows: w.report(now_ms) }).collect();
+        let delay = Self::estimate_delay(&stats, bps);
+        let stress = bps * bps * delay.delay_secs * (tip_width.max(1) as f64);
+        if stress > self.stress_peak && self.blocks.len() > 24 {
+            self.stress_peak = stress;
+        }
+        if self.synced {
+            // the stress index rests on Δ, so its sample is added to the windows already reported
+            self.observe("stress", now_ms, stress);
+            if let Some(s) = stats.iter_mut().find(|s| s.name == "stress") {
+                for w in &mut s.windows {
+                    w.observe(stress);
+                }
+            }
+        }
+        let (lat_mean, lat_p95) = headline(&stats, "merge_lat");
+        let (conf_time_mean, conf_time_p95) = headline(&stats, "conf_time");
+
+        if self.history.back().is_none_or(|h| now_ms - h.0 >= HISTORY_STEP_MS) {
+            self.history.push_back((now_ms, tip_width, bps, red_rate * 100.0, lat_mean));
+        }
+        while self.history.front().is_some_and(|h| now_ms - h.0 > HISTORY_MS) {
+            self.history.pop_front();
+        }
+        let window_secs = match (self.blocks.front(), self.blocks.back()) {
+            (Some(a), Some(b)) => round2((b.node.seen_ms - a.node.seen_ms).max(0) as f64 / 1000.0),
+            _ => 0.0,
+        };
 
         // Viz: most-recent `viz_cap` nodes by blue score.
         let total = self.blocks.len();
-        let tip_set: std::collections::HashSet<&String> = tips.iter().collect();
-        let mut all: Vec<&Tracked> = self.blocks.values().collect();
-        all.sort_by_key(|t| t.node.blue_score);
-        let shown = &all[all.len().saturating_sub(self.viz_cap)..];
-        let shown_ids: std::collections::HashSet<&str> = shown.iter().map(|t| t.node.hash.as_str()).collect();
-        let nodes: Vec<VizNode> = shown
+        let tip_set: HashSet<&Hash> = tips.iter().collect();
+        let mut shown: Vec<&Tracked> =
+            self.by_blue.iter().rev().take(self.viz_cap).filter_map(|&(_, _, id)| self.get(id)).collect();
+        shown.reverse();
+        let shown_ids: HashSet<&Hash> = shown.iter().map(|t| &t.node.hash).collect();
+        let mut nodes: Vec<VizNode> = shown
             .iter()
             .map(|t| VizNode {
-                id: short(&t.node.hash),
+                seq: 0,
+                id: t.node.hash.short(),
                 blue: t.node.blue_score,
                 daa: t.node.daa,
                 is_tip: tip_set.contains(&t.node.hash),
                 red: t.node.is_chain && t.node.reds > 0,
                 parents: t
-                    .node
                     .parents
                     .iter()
-                    .filter(|p| shown_ids.contains(p.as_str()))
-                    .map(|p| short(p))
+                    .filter_map(|&p| self.get(p))
+                    .map(|p| &p.node.hash)
+                    .filter(|p| shown_ids.contains(p))
+                    .map(Hash::short)
                     .collect(),
             })
             .collect();
 
+        // Stamp each shown node with the snapshot it last changed in, and log the ids that dropped out,
+        // so a client holding an earlier snapshot can be sent just the difference.
+        self.viz_seq += 1;
+        if self.viz_epoch == 0 {
+            self.viz_epoch = now_ms;
+        }
+        let seq = self.viz_seq;
+        let mut prev = std::mem::take(&mut self.viz_prev);
+        for n in &mut nodes {
+            n.seq = match prev.remove(&n.id) {
+                Some(old) if old.same(n) => old.seq,
+                _ => seq,
+            };
+        }
+        let mut dropped: Vec<String> = prev.into_keys().collect();
+        dropped.sort_unstable();
+        self.viz_removed.extend(dropped.into_iter().map(|id| (seq, id)));
+        while self.viz_removed.front().is_some_and(|(s, _)| s + VIZ_DELTA_SPAN <= seq) {
+            self.viz_removed.pop_front();
+        }
+        self.viz_prev = nodes.iter().map(|n| (n.id.clone(), n.clone())).collect();
+
+        let tc = &self.template_cov;
         Snapshot {
             connected: true,
-            network,
-            sink: short(&sink),
+            synced: self.synced,
+            conn_state: "connected".to_string(),
+            reconnects: 0,
+            last_good_ms: now_ms,
+            ingest: String::new(),
+            network: info.network.clone(),
+            sink: info.sink.short(),
             tip_width,
             peak_tip_width: self.peak_tip_width,
             bps: round2(bps),
-            virtual_daa,
-            block_count,
-            header_count,
-            difficulty: round2(difficulty),
+            virtual_daa: info.virtual_daa,
+            block_count: info.block_count,
+            header_count: info.header_count,
+            difficulty: round2(info.difficulty),
             blue_min,
             blue_max,
             blue_delta,
             max_parents,
             avg_parents: round2(avg_parents),
             tip_excess,
+            max_block_parents,
+            template_parents: tc.parents,
+            template_covered: tc.covered,
+            template_excluded: tc.left_out.len(),
+            template_limit: tc.limit,
+            template_age_secs: if tc.limit.is_empty() { 0.0 } else { round2((now_ms - tc.t_ms) as f64 / 1000.0) },
+            template_streak_secs: tc.left_out.first().map(|e| e.excluded_secs).unwrap_or(0.0),
+            template_max_streak_secs: round2(self.max_excluded_secs),
+            template_left_out: tc.left_out.iter().take(EXCLUDED_SHOWN).cloned().collect(),
             red_rate: round4(red_rate),
             reds_window: reds_w,
             blues_window: blues_w,
             merge_lat_mean: round2(lat_mean),
             merge_lat_p95: round2(lat_p95),
             merge_lat_max: lat_max,
+            merge_lat_hist: self.merge_lat_hist.clone(),
             merge_depth,
             depth_used_pct: round4(depth_used_pct),
+            pending_max_wait: pending_max,
+            orphans_new,
+            orphans_evicted: self.orphans_evicted,
+            orphans_depth: self.orphans_depth,
+            orphans: self.orphans.iter().rev().cloned().collect(),
             conf_depth,
             conf_time_mean: round2(conf_time_mean),
             conf_time_p95: round2(conf_time_p95),
             conf_corr: round4(conf_corr),
             conf_samples: self.conf_pairs.len(),
+            conf_hist: self.conf_hist.clone(),
+            delay,
             stress: round2(stress),
             stress_peak: round2(self.stress_peak),
             fracture,
+            fracture_proxy,
             fracture_secs: round2(fracture_secs),
             max_fracture_secs: round2(self.max_fracture_secs),
             fracture_events: self.fracture_events,
+            fracture_open,
+            fracture_ended,
+            fracture_log: self.fracture_log.iter().rev().cloned().collect(),
+            unordered_pairs,
+            unordered_oldest_secs,
+            unordered_max_gap,
+            resolved_pairs: self.resolved_pairs,
+            last_unordered_secs: round2(self.last_unordered_secs),
+            max_unordered_secs: round2(self.max_unordered_secs),
+            fracture_pairs: pairs.into_iter().take(FRACTURE_PAIRS_SHOWN).collect(),
+            reorg_count: self.reorg_count,
+            reorg_depth,
+            last_reorg_depth: self.reorgs.back().map(|r| r.depth).unwrap_or(0),
+            max_reorg_depth: self.max_reorg_depth,
+            max_reorg_blue: self.max_reorg_blue,
+            reorgs: self.reorgs.iter().rev().cloned().collect(),
+            recolour: Vec::new(),
             window: total,
+            window_secs,
             viz_shown: nodes.len(),
+            viz_epoch: self.viz_epoch,
+            viz_seq: seq,
+            viz_removed: self.viz_removed.iter().cloned().collect(),
             nodes,
-            tips: tips.iter().map(|t| short(t)).collect(),
-            tip_history: self.tip_history.iter().copied().collect(),
-            bps_history: self.bps_history.iter().map(|&b| round2(b)).collect(),
-            red_history: self.red_history.iter().map(|&r| round2(r)).collect(),
-            lat_history: self.lat_history.iter().map(|&l| round2(l)).collect(),
+            tips: tips.iter().map(Hash::short).collect(),
+            history_t: self.history.iter().map(|h| h.0).collect(),
+            tip_history: self.history.iter().map(|h| h.1).collect(),
+            bps_history: self.history.iter().map(|h| round2(h.2)).collect(),
+            red_history: self.history.iter().map(|h| round2(h.3)).collect(),
+            lat_history: self.history.iter().map(|h| round2(h.4)).collect(),
+            stats,
+            alerts: Vec::new(),
             updated_ms: now_ms,
         }
     }
 }
 
-fn latency_stats(q: &VecDeque<u64>, max: u64) -> (f64, f64, u64) {
-    if q.is_empty() {
-        return (0.0, 0.0, 0);
-    }
-    let mean = q.iter().sum::<u64>() as f64 / q.len() as f64;
-    let mut v: Vec<u64> = q.iter().copied().collect();
-    v.sort_unstable();
-    let p = v[((v.len() as f64 * 0.95) as usize).min(v.len() - 1)] as f64;
-    (mean, p, max)
+/// `name`'s (mean, p95) over the headline window.
+fn headline(stats: &[Series], name: &str) -> (f64, f64) {
+    stats
+        .iter()
+        .find(|s| s.name == name)
+        .and_then(|s| s.windows.get(HEADLINE))
+        .map(|w| (w.sketch.mean(), w.sketch.quantile(0.95)))
+        .unwrap_or_default()
 }
 
-fn mean(xs: &[f64]) -> f64 {
+// The statistics below are shared with `stitchbot analyze`, so the offline report and the live engine agree.
+pub(crate) fn mean(xs: &[f64]) -> f64 {
     if xs.is_empty() { 0.0 } else { xs.iter().sum::<f64>() / xs.len() as f64 }
 }
-fn p95(xs: &[f64]) -> f64 {
+/// Nearest-rank quantile (`q` in 0..=1).
+pub(crate) fn quantile(xs: &[f64], q: f64) -> f64 {
     if xs.is_empty() {
         return 0.0;
     }
     let mut v = xs.to_vec();
-    v.sort_by(|a, b| a.partial_cmp(b).unwrap());
-    v[((v.len() as f64 * 0.95) as usize).min(v.len() - 1)]
+    v.sort_by(|a, b| a.total_cmp(b));
+    v[((v.len() as f64 * q) as usize).min(v.len() - 1)]
 }
-/// Pearson correlation of (merge_lag, confirmation_secs).
-fn correlation(pairs: &VecDeque<(f64, f64)>) -> f64 {
-    let n = pairs.len();
+/// Pearson correlation of (x, y) pairs — here (merge_lag, confirmation_secs). 0 below 3 pairs or with no
+/// spread on either side.
+pub(crate) fn correlation<'a, I>(pairs: I) -> f64
+where
+    I: IntoIterator<Item = &'a (f64, f64)>,
+    I::IntoIter: Clone,
+{
+    let pairs = pairs.into_iter();
+    let n = pairs.clone().count();
     if n < 3 {
         return 0.0;
     }
     let (mx, my) = (
-        pairs.iter().map(|p| p.0).sum::<f64>() / n as f64,
-        pairs.iter().map(|p| p.1).sum::<f64>() / n as f64,
+        pairs.clone().map(|p| p.0).sum::<f64>() / n as f64,
+        pairs.clone().map(|p| p.1).sum::<f64>() / n as f64,
     );
     let mut cov = 0.0;
     let mut dx = 0.0;
@@ -342,23 +1295,28 @@ fn push_bounded<T>(q: &mut VecDeque<T>, v: T, limit: usize) {
         q.pop_front();
     }
 }
-fn short(h: &str) -> String {
-    h.chars().take(10).collect()
+fn ratio(num: u64, den: u64) -> f64 {
+    if den > 0 { num as f64 / den as f64 } else { 0.0 }
 }
-fn round2(x: f64) -> f64 {
+pub(crate) fn round2(x: f64) -> f64 {
     (x * 100.0).round() / 100.0
 }
-fn round4(x: f64) -> f64 {
+pub(crate) fn round4(x: f64) -> f64 {
     (x * 10000.0).round() / 10000.0
 }
 
 #[derive(Serialize, Clone, Default)]
 pub struct Snapshot {
     pub connected: bool,
+    pub synced: bool, // false while the node catches up: not logged, stored or alerted on
+    pub conn_state: String, // "connecting" | "connected" | "stale" | "disconnected"
+    pub reconnects: u64,
+    pub last_good_ms: i64, // node last seen advancing
+    pub ingest: String,
     pub network: String,
     pub sink: String,
     pub tip_width: usize,
-    pub peak_tip_width: usize,
+    pub peak_tip_width: usize, // since start; windowed maxima are in `stats`
     pub bps: f64,
     pub virtual_daa: u64,
     pub block_count: u64,
@@ -370,38 +1328,110 @@ pub struct Snapshot {
     pub max_parents: usize,
     pub avg_parents: f64,
     pub tip_excess: usize,
+    pub max_block_parents: usize,
+    pub template_parents: usize,   // parents of the latest block template
+    pub template_covered: usize,   // live tips it references
+    pub template_excluded: usize,  // live tips it leaves out
+    pub template_limit: &'static str, // "" = templates off; "ok", or why tips are left out: "cap" / "policy"
+    pub template_age_secs: f64,
+    pub template_streak_secs: f64,     // longest current run of a tip being left out
+    pub template_max_streak_secs: f64, // longest ever
+    pub template_left_out: Vec<ExcludedTip>,
     pub red_rate: f64,
     pub reds_window: u64,
     pub blues_window: u64,
-    pub merge_lat_mean: f64,
+    pub merge_lat_mean: f64, // mean / p95 over the last 15 min (the "15m" window of `stats`)
     pub merge_lat_p95: f64,
-    pub merge_lat_max: u64,
+    pub merge_lat_max: u64, // since start
+    #[serde(skip)]
+    pub merge_lat_hist: Histogram, // since start — for /metrics
     pub merge_depth: u64,
     pub depth_used_pct: f64,
+    pub pending_max_wait: u64, // rounds the longest-waiting still-unmerged block has waited
+    pub orphans_new: u64,      // permanent orphans found since the previous snapshot
+    pub orphans_evicted: u64,  // left the window unmerged (upper bound: the window may be shorter than the lag)
+    pub orphans_depth: u64,    // passed merge_depth rounds unmerged
+    pub orphans: Vec<Orphan>,  // most recent first
     pub conf_depth: u64,
-    pub conf_time_mean: f64,
+    pub conf_time_mean: f64, // over the last 15 min, like merge_lat_mean
     pub conf_time_p95: f64,
     pub conf_corr: f64,
     pub conf_samples: usize,
-    pub stress: f64,
-    pub stress_peak: f64,
+    #[serde(skip)]
+    pub conf_hist: Histogram, // since start — for /metrics
+    #[serde(flatten)]
+    pub delay: DelayEstimate, // delay_secs, its bounds and cross-checks
+    pub stress: f64, // bps² × Δ (measured, `delay_secs`) × tip width
+    pub stress_peak: f64, // since start
     pub fracture: bool,
+    pub fracture_proxy: bool,
     pub fracture_secs: f64,
-    pub max_fracture_secs: f64,
+    pub max_fracture_secs: f64, // since start
     pub fracture_events: u64,
-    pub window: usize,
+    #[serde(skip)]
+    pub fracture_open: Option<FractureEpisode>, // the episode in progress, so far (`/api/fractures`)
+    #[serde(skip)]
+    pub fracture_ended: Option<FractureEpisode>, // the episode that ended at this snapshot
+    #[serde(skip)]
+    pub fracture_log: Vec<FractureEpisode>, // most recent first
+    pub unordered_pairs: usize,
+    pub unordered_oldest_secs: f64,
+    pub unordered_max_gap: u64,
+    pub resolved_pairs: u64,
+    pub last_unordered_secs: f64,
+    pub max_unordered_secs: f64,
+    pub fracture_pairs: Vec<FracturePair>,
+    pub reorg_count: u64,
+    pub reorg_depth: usize, // deepest reorg since the previous snapshot (0 = none)
+    pub last_reorg_depth: usize,
+    pub max_reorg_depth: usize,
+    pub max_reorg_blue: u64,
+    pub reorgs: Vec<Reorg>, // most recent first
+    pub recolour: Vec<RecolourReport>, // local GHOSTDAG cross-check, one per configured k (empty = off)
+    pub window: usize,      // blocks in the rolling window (`dag_window`)…
+    pub window_secs: f64,   // …and the first-seen time they span
     pub viz_shown: usize,
+    pub viz_epoch: i64,
+    pub viz_seq: u64, // this snapshot's viz version; pass it back as `/api?since=` for a delta
+    #[serde(skip)]
+    pub viz_removed: Vec<(u64, String)>, // ids dropped from `nodes` in recent snapshots, oldest first
     pub nodes: Vec<VizNode>,
     pub tips: Vec<String>,
+    pub history_t: Vec<i64>, // sparkline point times: at most one a second, the last 2 min
     pub tip_history: Vec<usize>,
     pub bps_history: Vec<f64>,
     pub red_history: Vec<f64>,
     pub lat_history: Vec<f64>,
+    pub stats: Vec<Series>, // every windowed statistic: p50 … p99.9, mean, max and count per window
+    pub alerts: Vec<AlertStatus>, // rule states; filled in by the ingest loop, not the engine
     pub updated_ms: i64,
 }
 
+/// The measured effective propagation delay Δ (see `Engine::estimate_delay`), over the last 1–2 min.
+#[derive(Serialize, Clone, Default)]
+pub struct DelayEstimate {
+    pub delay_secs: f64,            // what the stress index uses
+    pub delay_lo_secs: f64,         // ~95% bounds
+    pub delay_hi_secs: f64,
+    pub delay_samples: u64,         // first-child gaps behind it
+    pub delay_source: &'static str, // "child_gap", or "prior" (too few samples: Δ = 0.9 s)
+    pub delay_tips_secs: f64,       // cross-check from tip width: (mean tips − 1) / λ
+    pub delay_seen_secs: f64,       // median header-to-first-seen lag here (adds poll delay and clock skew)
+}
+
+/// A live tip the latest block template did not reference.
+#[derive(Serialize, Clone)]
+pub struct ExcludedTip {
+    pub hash: String,
+    pub blue_score: u64,
+    pub excluded_secs: f64, // since the first template in a row that left it out
+    pub templates: u32,     // consecutive templates that left it out
+}
+
 #[derive(Serialize, Clone)]
 pub struct VizNode {
+    #[serde(skip)]
+    pub seq: u64, // viz_seq of the snapshot it was added or last changed in
     pub id: String,
     pub blue: u64,
     pub daa: u64,
@@ -409,3 +1439,242 @@ pub struct VizNode {
     pub red: bool,
     pub parents: Vec<String>,
 }
+
+impl VizNode {
+    fn same(&self, o: &VizNode) -> bool {
+        (self.blue, self.daa, self.is_tip, self.red, &self.parents) == (o.blue, o.daa, o.is_tip, o.red, &o.parents)
+    }
+}
+
+/// What changed in the viz since snapshot `since` of the same run: nodes added or changed (tip flag, red
+/// flag, parents still in view) and ids removed. Absent when `since` is too old, from another run, or
+/// ahead of this snapshot — send the full `nodes` then.
+pub struct VizDelta<'a> {
+    pub nodes: Vec<&'a VizNode>,
+    pub removed: Vec<&'a str>,
+}
+
+impl Snapshot {
+    pub fn viz_delta(&self, epoch: i64, since: u64) -> Option<VizDelta<'_>> {
+        if epoch != self.viz_epoch || since > self.viz_seq || since + VIZ_DELTA_SPAN < self.viz_seq {
+            return None;
+        }
+        Some(VizDelta {
+            nodes: self.nodes.iter().filter(|n| n.seq > since).collect(),
+            removed: self.viz_removed.iter().filter(|(s, _)| *s > since).map(|(_, id)| id.as_str()).collect(),
+        })
+    }
+}
+
+/// Two high-blue blocks currently in each other's anticone (no block in the window merges both yet).
+#[derive(Serialize, Clone)]
+pub struct FracturePair {
+    pub a: String,
+    pub b: String,
+    pub blue_gap: u64,
+    pub unordered_secs: f64, // since the later of the two was produced (header time)
+    #[serde(skip)]
+    key: (Hash, Hash), // lower first
+}
+
+/// One fracture from the first snapshot that saw it to the first that didn't. `ended_by` is the block
+/// that finally ordered the last of its unordered pairs (first block with both in its past), and
+/// `resolve_secs` that pair's unordered time by header time — the README's D_f, measured per episode.
+#[derive(Serialize, Clone)]
+pub struct FractureEpisode {
+    pub id: u64, // = fracture_events when it started
+    pub start_ms: i64,
+    pub end_ms: Option<i64>, // None while it lasts
+    pub secs: f64,
+    pub peak_tip_width: usize,
+    pub peak_blue_delta: u64,
+    pub peak_blue_gap: u64, // largest blue gap across the pairs that held it open
+    pub pairs: usize,       // distinct unordered pairs that held it open
+    pub tips: Vec<String>,  // tips seen during it (first EPISODE_TIPS)
+    pub ended_by: Option<String>,
+    pub ended_by_parents: usize,
+    pub resolve_secs: Option<f64>,
+    pub reds: u32, // blocks produced during it that were merged as red (so far, while it lasts)
+}
+
+/// One selected-chain reorganization: chain blocks that left (`removed`, old sink first) and joined
+/// (`added`, fork side first) the virtual chain.
+#[derive(Serialize, Clone)]
+pub struct Reorg {
+    pub t_ms: i64,
+    pub depth: usize,    // chain blocks removed
+    pub blue_depth: u64, // old sink's blue score minus the fork point's
+    pub old_sink: String,
+    pub new_sink: String,
+    pub fork: String,
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+/// A block that will never be merged: it left the window unmerged (`evicted`) or waited past the merge
+/// depth (`merge_depth`).
+#[derive(Serialize, Clone)]
+pub struct Orphan {
+    pub hash: String,
+    pub blue_score: u64,
+    pub parents: usize,
+    pub age_secs: f64, // since production (header time) when it was declared orphaned
+    pub rounds: u64,   // blue rounds below the frontier at that moment
+    pub reason: &'static str,
+}
+
+impl Orphan {
+    fn of(t: &Tracked, reason: &'static str, frontier: u64, now_ms: i64) -> Self {
+        Orphan {
+            hash: t.node.hash.to_string(),
+            blue_score: t.node.blue_score,
+            parents: t.parent_count,
+            age_secs: round2((now_ms - t.node.timestamp as i64).max(0) as f64 / 1000.0),
+            rounds: frontier.saturating_sub(t.node.blue_score),
+            reason,
+        }
+    }
+}
+
+/// Local GHOSTDAG colouring at one `k` versus the node. `red_rate` / `node_red_rate` are over the same
+/// checked chain-block mergesets, so a different `k` can be compared like-for-like.
+#[derive(Serialize, Clone, Default)]
+pub struct RecolourReport {
+    pub k: u64,
+    pub checked: usize,
+    pub disagreements: usize,
+    pub red_rate: f64,
+    pub node_red_rate: f64,
+    pub samples: Vec<ColourDiff>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ColourDiff {
+    pub hash: String,
+    pub blue_score: u64,
+    pub score_diff: i64, // local minus node blue-score increment
+    pub local_only_blue: Vec<String>,
+    pub node_only_blue: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn h(n: u8) -> Hash {
+        Hash([n; 32])
+    }
+
+    /// Ingest `(hash, selected parent, blue score)` blocks into a synced engine.
+    fn engine(blocks: &[(u8, Option<u8>, u64)]) -> Engine {
+        let mut eng = Engine::new(64, 50);
+        eng.synced = true;
+        for &(n, parent, blue) in blocks {
+            let node = BlockNode {
+                hash: h(n),
+                blue_score: blue,
+                daa: blue,
+                timestamp: blue * 100,
+                seen_ms: blue as i64 * 100,
+                parents: parent.map(h).into_iter().collect(),
+                selected_parent: parent.map(h).unwrap_or_default(),
+                is_chain: false,
+                blues: 1,
+                reds: 0,
+            };
+            eng.ingest(node, Vec::new());
+        }
+        eng
+    }
+
+    // G ← A1 ← A2 and G ← B1 ← B2 ← B3 ← B4
+    const FORK: &[(u8, Option<u8>, u64)] =
+        &[(1, None, 0), (10, Some(1), 1), (11, Some(10), 2), (20, Some(1), 1), (21, Some(20), 2), (22, Some(21), 3), (23, Some(22), 4)];
+
+    #[test]
+    fn sink_switch_to_a_sibling_branch_is_a_reorg() {
+        let mut eng = engine(FORK);
+        eng.track_chain(&h(11), 1000);
+        assert_eq!(eng.chain(), [h(11), h(10), h(1)]);
+        eng.track_chain(&h(22), 2000);
+        assert_eq!(eng.reorg_count, 1);
+        let r = eng.reorgs.back().unwrap();
+        assert_eq!((r.depth, r.blue_depth, r.t_ms), (2, 2, 2000));
+        assert_eq!([&r.old_sink, &r.new_sink, &r.fork], [&h(11).short(), &h(22).short(), &h(1).short()]);
+        assert_eq!(r.removed, [h(11).short(), h(10).short()]);
+        assert_eq!(r.added, [h(20).short(), h(21).short(), h(22).short()]);
+        assert_eq!((eng.max_reorg_depth, eng.max_reorg_blue, eng.reorg_depth_since_snapshot), (2, 2, 2));
+
+        // extending the new chain is not one
+        eng.track_chain(&h(23), 3000);
+        assert_eq!(eng.reorg_count, 1);
+        assert_eq!(eng.chain()[0], h(23));
+    }
+
+    #[test]
+    fn unreachable_old_chain_resyncs_without_a_reorg() {
+        // X1 ← X2 hangs off a parent the window never saw
+        let mut blocks = FORK.to_vec();
+        blocks.extend([(30, Some(99), 5), (31, Some(30), 6)]);
+        let mut eng = engine(&blocks);
+        eng.track_chain(&h(11), 1000);
+        eng.track_chain(&h(31), 2000);
+        assert_eq!(eng.reorg_count, 0);
+        assert!(eng.reorgs.is_empty());
+        assert_eq!(eng.chain(), [h(31), h(30)]);
+    }
+
+    #[test]
+    fn reorgs_while_syncing_are_not_counted() {
+        let mut eng = engine(FORK);
+        eng.synced = false;
+        eng.track_chain(&h(11), 1000);
+        eng.track_chain(&h(22), 2000);
+        assert_eq!((eng.reorg_count, eng.max_reorg_depth), (0, 0));
+        assert_eq!(eng.chain()[0], h(22));
+    }
+
+    #[test]
+    fn chain_changes_keep_the_window_sums() {
+        let mut eng = engine(FORK);
+        // a merging block with reds, so the parent counts and chain totals aren't all alike
+        let merger = BlockNode {
+            hash: h(24),
+            blue_score: 5,
+            daa: 5,
+            timestamp: 500,
+            seen_ms: 500,
+            parents: vec![h(23), h(11)],
+            selected_parent: h(23),
+            is_chain: false,
+            blues: 3,
+            reds: 2,
+        };
+        eng.ingest(merger, Vec::new());
+        let sums = |eng: &Engine| {
+            let s = &eng.sums;
+            (s.parents, s.parent_counts.clone(), s.chain_blues, s.chain_reds)
+        };
+        let rescan = |eng: &Engine| {
+            let mut s = WindowSums::default();
+            for t in &eng.blocks {
+                s.add(&t.node, t.parent_count);
+            }
+            (s.parents, s.parent_counts, s.chain_blues, s.chain_reds)
+        };
+
+        eng.chain_changed(&[], &[h(1), h(10), h(11)], 1000);
+        assert_eq!(sums(&eng), rescan(&eng));
+        assert_eq!((eng.sums.chain_blues, eng.sums.chain_reds), (3, 0));
+
+        eng.chain_changed(&[h(11), h(10)], &[h(20), h(21), h(22), h(23), h(24)], 2000);
+        assert_eq!(sums(&eng), rescan(&eng));
+        assert_eq!((eng.sums.chain_blues, eng.sums.chain_reds), (8, 2));
+        assert!(!eng.find(&h(10)).unwrap().node.is_chain && eng.find(&h(24)).unwrap().node.is_chain);
+
+        // repeating a flip changes nothing
+        eng.chain_changed(&[], &[h(24)], 3000);
+        assert_eq!(sums(&eng), rescan(&eng));
+        assert_eq!(eng.sums.parents, 8);
+    }
+}
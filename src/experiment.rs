@@ -0,0 +1,121 @@
+//! Parent-selection policy experiment (`stitchbot experiment`). The same traffic — a simulator run with a
+//! fixed seed, or a capture re-mined at its recorded times — is played once per policy through the full
+//! engine, and the resulting tip width, merge latency and red rate are compared side by side. Policies only
+//! differ once a miner sees more tips than `max_parents`, so sweep that cap too.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::engine::Snapshot;
+use crate::sim::{Policy, SimSource};
+use crate::source::{BlockSource, Pipeline};
+
+const WARMUP: usize = 10; // snapshots skipped while the window fills
+
+/// One policy's outcome over the run, after the warm-up. Latency quantiles are over every merged block.
+#[derive(Serialize, Default)]
+pub struct Outcome {
+    pub policy: &'static str,
+    pub snapshots: usize,
+    pub tips_mean: f64,
+    pub tips_p95: usize,
+    pub tips_max: usize,
+    pub avg_parents: f64,
+    pub merge_lat_mean: f64, // blue rounds
+    pub merge_lat_p95: f64,
+    pub red_rate: f64,
+    pub unordered_pairs: f64,
+    pub reorgs: u64,
+    pub orphans: u64,
+}
+
+/// `stitchbot experiment [--capture FILE | --secs S] [--policies a,b,..] [--max-parents N] [--json]`
+pub async fn run(cfg: &Config, args: &[String]) -> Result<()> {
+    let flag = |name: &str| crate::flag(args, name);
+    let mut cfg = cfg.clone();
+    if let Some(n) = flag("--max-parents") {
+        cfg.sim_max_parents = n.parse()?;
+    }
+    let policies = match flag("--policies") {
+        Some(list) => list.split(',').map(|p| Policy::parse(p.trim())).collect::<Result<Vec<_>>>()?,
+        None => Policy::ALL.to_vec(),
+    };
+    let capture = flag("--capture");
+    let secs: f64 = flag("--secs").map(|s| s.parse()).transpose()?.unwrap_or(600.0);
+
+    let mut out = Vec::new();
+    for p in policies {
+        let src = match &capture {
+            Some(path) => SimSource::from_capture(&cfg, path, p).await?,
+            None => SimSource::new(&cfg, p, 0.0, Some(secs))?,
+        };
+        log::info!("experiment: {} over {} …", p.name(), capture.as_deref().unwrap_or("simulated traffic"));
+        out.push(measure(src, p, &cfg).await?);
+    }
+
+    if args.iter().any(|a| a == "--json") {
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+    println!(
+        "max_parents {}  k {}  {}",
+        cfg.sim_max_parents,
+        cfg.sim_k,
+        match &capture {
+            Some(path) => format!("capture {path}"),
+            None => format!("λ {} BPS  Δ {} ms ({})  {} miners  {secs} s  seed {}", cfg.sim_bps, cfg.sim_delay_ms, cfg.sim_delay_dist, cfg.sim_miners, cfg.sim_seed),
+        }
+    );
+    println!(
+        "{:<16} {:>8} {:>8} {:>8} {:>8} {:>10} {:>10} {:>8} {:>10} {:>7} {:>8}",
+        "policy", "tips", "tips95", "tipsmax", "parents", "lat_mean", "lat_p95", "red", "unordered", "reorgs", "orphans"
+    );
+    for o in &out {
+        println!(
+            "{:<16} {:>8.2} {:>8} {:>8} {:>8.2} {:>10.2} {:>10.2} {:>8.4} {:>10.2} {:>7} {:>8}",
+            o.policy, o.tips_mean, o.tips_p95, o.tips_max, o.avg_parents, o.merge_lat_mean, o.merge_lat_p95,
+            o.red_rate, o.unordered_pairs, o.reorgs, o.orphans
+        );
+    }
+    Ok(())
+}
+
+/// Drive one source to exhaustion through a fresh engine and summarise it past the warm-up: running sums
+/// for the per-snapshot means, and the engine's run-long sketches for the distributions.
+async fn measure<S: BlockSource>(mut src: S, policy: Policy, cfg: &Config) -> Result<Outcome> {
+    let mut pipe = Pipeline::new(cfg);
+    let (mut seen, mut body) = (0usize, 0usize);
+    let (mut parents, mut red, mut unordered) = (0.0, 0.0, 0.0);
+    let mut last = Snapshot::default();
+    while let Some(ev) = src.next().await? {
+        let Some(s) = pipe.feed(ev, cfg) else { continue };
+        seen += 1;
+        if seen == WARMUP {
+            pipe.eng.keep_totals(); // samples from the next snapshot on
+        } else if seen > WARMUP {
+            body += 1;
+            parents += s.avg_parents;
+            red += s.red_rate;
+            unordered += s.unordered_pairs as f64;
+        }
+        last = s;
+    }
+    let n = body.max(1) as f64;
+    let tips = pipe.eng.total("tip_width");
+    let lat = pipe.eng.total("merge_lat");
+    Ok(Outcome {
+        policy: policy.name(),
+        snapshots: body,
+        tips_mean: tips.mean(),
+        tips_p95: tips.quantile(0.95).round() as usize,
+        tips_max: tips.max() as usize,
+        avg_parents: parents / n,
+        merge_lat_mean: lat.mean(),
+        merge_lat_p95: lat.quantile(0.95),
+        red_rate: red / n,
+        unordered_pairs: unordered / n,
+        reorgs: last.reorg_count,
+        orphans: last.orphans_evicted + last.orphans_depth,
+    })
+}
@@ -0,0 +1,232 @@
+//! GHOSTDAG k-cluster colouring over a bounded DAG, independent of the node.
+//!
+//! Blocks are added in topological order; each gets a selected parent (highest blue score, then hash),
+//! a mergeset (its past minus the selected parent's closed past) and a blue/red colouring by the
+//! k-cluster rule, following rusty-kaspa's `ghostdag::protocol`. Blue score stands in for blue work
+//! (equal difficulty). A block whose mergeset or colouring walk runs into the edge of what we hold is
+//! flagged `truncated`: its verdict can't be trusted and callers should skip it.
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::hash::Hash;
+
+const SP: u8 = 1; // reached from the selected parent: in its closed past
+const MS: u8 = 2; // reached from another parent only (so far): mergeset candidate
+
+/// Colouring result for one block.
+#[derive(Clone, Default)]
+pub struct GhostdagData {
+    pub seq: u64,
+    pub parents: Vec<Hash>, // parents we hold
+    pub selected_parent: Option<Hash>,
+    pub blue_score: u64,
+    pub blues: Vec<Hash>, // mergeset blues, selected parent first
+    pub reds: Vec<Hash>,
+    pub truncated: bool,
+    partial: bool, // some parents not held
+    blues_anticone_sizes: HashMap<Hash, u64>,
+}
+
+pub struct Ghostdag {
+    k: u64,
+    blocks: HashMap<Hash, GhostdagData>,
+    seq: u64,
+}
+
+impl Ghostdag {
+    pub fn new(k: u64) -> Self {
+        Self { k, blocks: HashMap::new(), seq: 0 }
+    }
+
+    /// Colour a new block. Parents we don't hold are dropped (and mark the block truncated), as do
+    /// `missing` ones the caller already knows it lacks; a block with none becomes a root whose blue score
+    /// is `base_blue_score`.
+    pub fn add(&mut self, hash: Hash, parents: &[Hash], missing: usize, base_blue_score: u64) -> &GhostdagData {
+        let known: Vec<Hash> = parents.iter().filter(|p| self.blocks.contains_key(*p)).copied().collect();
+        let partial = missing > 0 || known.len() < parents.len();
+        let mut data = GhostdagData {
+            seq: self.seq,
+            truncated: partial,
+            partial,
+            parents: known,
+            ..Default::default()
+        };
+        self.seq += 1;
+
+        let sp = data
+            .parents
+            .iter()
+            .max_by(|a, b| (self.blocks[*a].blue_score, *a).cmp(&(self.blocks[*b].blue_score, *b)))
+            .copied();
+        match sp {
+            None => {
+                data.blue_score = base_blue_score;
+                data.truncated = true;
+            }
+            Some(sp) => {
+                let (mut mergeset, edge) = self.mergeset(sp, &data.parents);
+                data.truncated |= edge;
+                mergeset.sort_by(|a, b| (self.blocks[a].blue_score, a).cmp(&(self.blocks[b].blue_score, b)));
+                data.blues.push(sp);
+                data.blues_anticone_sizes.insert(sp, 0);
+                data.selected_parent = Some(sp);
+                let mut edge = false;
+                for c in mergeset {
+                    match self.check_blue_candidate(&data, c, &mut edge) {
+                        Some((size, sizes)) => {
+                            data.blues_anticone_sizes.insert(c, size);
+                            for (b, s) in sizes {
+                                data.blues_anticone_sizes.insert(b, s + 1);
+                            }
+                            data.blues.push(c);
+                        }
+                        None => data.reds.push(c),
+                    }
+                }
+                data.blue_score = self.blocks[&sp].blue_score + data.blues.len() as u64;
+                data.truncated |= edge;
+            }
+        }
+        self.blocks.insert(hash, data);
+        &self.blocks[&hash]
+    }
+
+    pub fn get(&self, hash: &Hash) -> Option<&GhostdagData> {
+        self.blocks.get(hash)
+    }
+
+    /// Forget a block (pruning from the old end). Later walks simply stop where it was.
+    pub fn remove(&mut self, hash: &Hash) {
+        self.blocks.remove(hash);
+    }
+
+    /// past(block) \ closed-past(sp), walking both sides downward in insertion order: a block is popped
+    /// only after all its held descendants, so if it is in the selected parent's past it has been reached
+    /// from that side by then. Returns the mergeset (without `sp`) and whether the walk touched a block
+    /// whose parents we only partly hold.
+    fn mergeset(&self, sp: Hash, parents: &[Hash]) -> (Vec<Hash>, bool) {
+        let mut flags: HashMap<Hash, u8> = HashMap::new();
+        let mut heap: BinaryHeap<(u64, Hash)> = BinaryHeap::new();
+        let mut ms_only = 0isize;
+        ms_only += self.mark(sp, SP, &mut flags, &mut heap);
+        for &p in parents.iter().filter(|&&p| p != sp) {
+            ms_only += self.mark(p, MS, &mut flags, &mut heap);
+        }
+        let mut out = Vec::new();
+        let mut edge = false;
+        while ms_only > 0 {
+            let Some((_, h)) = heap.pop() else { break };
+            let f = flags[&h];
+            let d = &self.blocks[&h];
+            // a partly-held block on either side may hide a path that would change the split
+            edge |= d.partial;
+            if f == MS {
+                ms_only -= 1;
+                out.push(h);
+            }
+            let side = if f & SP != 0 { SP } else { MS };
+            for &p in &d.parents {
+                ms_only += self.mark(p, side, &mut flags, &mut heap);
+            }
+        }
+        (out, edge)
+    }
+
+    /// OR `f` into `h`'s traversal flags, queueing it on first touch. Returns the change in the number of
+    /// queued blocks reached from the mergeset side only.
+    fn mark(&self, h: Hash, f: u8, flags: &mut HashMap<Hash, u8>, heap: &mut BinaryHeap<(u64, Hash)>) -> isize {
+        let Some(d) = self.blocks.get(&h) else { return 0 };
+        let e = flags.entry(h).or_insert(0);
+        let (old, new) = (*e, *e | f);
+        *e = new;
+        if old == 0 {
+            heap.push((d.seq, h));
+        }
+        match (old, new) {
+            (0, MS) => 1,
+            (MS, n) if n != MS => -1,
+            _ => 0,
+        }
+    }
+
+    /// The k-cluster test for `c` against the blues of `new` and of its selected chain: blue iff the
+    /// blue anticone of `c` stays ≤ k and adding `c` pushes no blue's own blue anticone past k. On
+    /// success returns `c`'s blue-anticone size and the blues whose anticone it joins. Sets `edge` if the
+    /// chain walk ran off the blocks we hold before settling.
+    fn check_blue_candidate(
+        &self,
+        new: &GhostdagData,
+        c: Hash,
+        edge: &mut bool,
+    ) -> Option<(u64, HashMap<Hash, u64>)> {
+        if new.blues.len() as u64 == self.k + 1 {
+            return None;
+        }
+        let mut sizes: HashMap<Hash, u64> = HashMap::new();
+        let mut size = 0u64;
+        let mut chain: Option<(Hash, &GhostdagData)> = None; // None = the new block itself
+        loop {
+            let data = chain.map(|(_, d)| d).unwrap_or(new);
+            if let Some((h, _)) = chain
+                && self.is_ancestor(h, c)
+            {
+                break;
+            }
+            for &b in &data.blues {
+                if self.is_ancestor(b, c) {
+                    continue;
+                }
+                let bs = self.blue_anticone_size(b, new);
+                sizes.insert(b, bs);
+                size += 1;
+                if size > self.k || bs == self.k {
+                    return None;
+                }
+            }
+            match data.selected_parent.and_then(|sp| self.blocks.get(&sp).map(|d| (sp, d))) {
+                Some(next) => chain = Some(next),
+                None => {
+                    *edge = true; // ran off the held DAG: provisionally blue, verdict untrusted
+                    break;
+                }
+            }
+        }
+        Some((size, sizes))
+    }
+
+    /// Blue-anticone size of `b` as seen from `ctx`: the first entry found walking ctx's selected chain.
+    fn blue_anticone_size(&self, b: Hash, ctx: &GhostdagData) -> u64 {
+        let mut cur = Some(ctx);
+        while let Some(d) = cur {
+            if let Some(&s) = d.blues_anticone_sizes.get(&b) {
+                return s;
+            }
+            cur = d.selected_parent.and_then(|sp| self.blocks.get(&sp));
+        }
+        0
+    }
+
+    /// Strict DAG ancestry: `a` ∈ past(`c`). Only blocks inserted after `a` can lie on a path down to it.
+    pub fn is_ancestor(&self, a: Hash, c: Hash) -> bool {
+        let (Some(da), Some(dc)) = (self.blocks.get(&a), self.blocks.get(&c)) else { return false };
+        if da.seq >= dc.seq {
+            return false;
+        }
+        let mut seen: HashSet<Hash> = HashSet::new();
+        let mut stack: Vec<&GhostdagData> = vec![dc];
+        while let Some(d) = stack.pop() {
+            for &p in &d.parents {
+                if p == a {
+                    return true;
+                }
+                if let Some(dp) = self.blocks.get(&p)
+                    && dp.seq > da.seq
+                    && seen.insert(p)
+                {
+                    stack.push(dp);
+                }
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,359 @@
+//! The live block source: a Kaspa node over gRPC, polled (get_blocks every tick) or pushed (block-added /
+//! virtual-chain-changed notifications between ticks). Each tick ends with the node's DAG info.
+//!
+//! The connection is supervised: it is made lazily and retried with capped exponential backoff, and it is
+//! dropped and remade after any failed tick, when the notification stream dies, and when the node still
+//! answers but has stopped advancing (virtual DAA score and sink unchanged for `stale_ms`). Each failure
+//! surfaces as one `next()` error, so the monitor shows the node down while it retries.
+
+use std::collections::{HashSet, VecDeque};
+use anyhow::{anyhow, bail, Result};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+use kaspa_grpc_client::GrpcClient;
+use kaspa_notify::listener::ListenerId;
+use kaspa_notify::scope::{BlockAddedScope, Scope, VirtualChainChangedScope};
+use kaspa_rpc_core::api::rpc::RpcApi;
+use kaspa_rpc_core::{Notification, RpcAddress, RpcBlock, RpcHash};
+
+use crate::config::Config;
+use crate::engine::{BlockNode, DagInfo};
+use crate::hash::Hash;
+use crate::source::{BlockSource, Link, SourceEvent};
+
+const BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RPC_TIMEOUT: Duration = Duration::from_secs(10); // a whole tick; a hung call counts as a dead link
+
+pub struct GrpcSource {
+    url: String,
+    notify: bool, // ingest = "notify"
+    client: Option<GrpcClient>,
+    link: Link,
+    backoff: Duration,
+    backoff_max: Duration,
+    retry_at: Instant,
+    stale_after: Option<Duration>,
+    progress: Option<(u64, RpcHash, Instant)>, // virtual DAA, sink, and when the stale clock for them started
+    synced: Option<bool>, // last sync state passed on (None = not yet on this connection)
+    notes: Option<mpsc::UnboundedReceiver<Notification>>,
+    pending: VecDeque<SourceEvent>,
+    low_hash: Option<RpcHash>,
+    emitted: HashSet<RpcHash>, // recently emitted blocks, so tips aren't re-fetched every poll
+    emitted_order: VecDeque<RpcHash>,
+    emitted_cap: usize,
+    period: Duration,
+    next_tick: Instant,
+    template: Option<(RpcAddress, Duration)>, // pay address + cadence for block-template coverage
+    next_template: Instant,
+}
+
+impl GrpcSource {
+    /// Set up the source; the node is first contacted on the first `next()`, so a node that is down at
+    /// startup is waited for rather than fatal.
+    pub fn new(cfg: &Config) -> Result<Self> {
+        let template = match cfg.template_pay_address.as_str() {
+            "" => None,
+            a => {
+                let addr = RpcAddress::try_from(a).map_err(|e| anyhow!("template_pay_address {a}: {e}"))?;
+                Some((addr, Duration::from_millis(cfg.template_ms.max(cfg.poll_ms))))
+            }
+        };
+
+        Ok(Self {
+            url: format!("grpc://{}", cfg.rpc_url),
+            notify: cfg.ingest == "notify",
+            client: None,
+            link: Link { state: "connecting", reconnects: 0, last_good_ms: 0 },
+            backoff: BACKOFF_MIN,
+            backoff_max: Duration::from_millis(cfg.reconnect_max_ms).max(BACKOFF_MIN),
+            retry_at: Instant::now(),
+            stale_after: (cfg.stale_ms > 0).then(|| Duration::from_millis(cfg.stale_ms)),
+            progress: None,
+            synced: None,
+            notes: None,
+            pending: VecDeque::new(),
+            low_hash: None,
+            emitted: HashSet::new(),
+            emitted_order: VecDeque::new(),
+            emitted_cap: cfg.dag_window.max(16),
+            period: Duration::from_millis(cfg.poll_ms),
+            next_tick: Instant::now(),
+            template,
+            next_template: Instant::now(),
+        })
+    }
+
+    /// Connect once the backoff has passed (and, in push mode, subscribe before the first poll so no block
+    /// slips between the two). A failure pushes the next attempt further out.
+    async fn connect(&mut self) -> Result<()> {
+        tokio::time::sleep_until(self.retry_at).await;
+        log::info!("connecting to node at {} ...", self.url);
+        let client = match GrpcClient::connect(self.url.clone()).await {
+            Ok(c) => c,
+            Err(e) => {
+                self.fail(if self.link.state == "connecting" { "connecting" } else { "disconnected" });
+                bail!("connect {}: {e}", self.url);
+            }
+        };
+        if self.notify {
+            match subscribe(&client).await {
+                Ok(rx) => self.notes = Some(rx),
+                Err(e) => log::warn!("notification subscribe failed ({e}) — falling back to polling"),
+            }
+        }
+        if self.link.state != "connecting" {
+            self.link.reconnects += 1;
+        }
+        log::info!("connected to {} — {} ingestion", self.url, self.mode());
+        self.link.state = "connected";
+        self.client = Some(client);
+        self.next_tick = Instant::now();
+        // a fresh connection starts its stale clock anew, but keeps the last DAA and sink: reconnecting to a
+        // node that still hasn't moved is not progress, and must not reset the backoff
+        if let Some(p) = &mut self.progress {
+            p.2 = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Drop the connection (if any) and schedule the next attempt, doubling the backoff.
+    fn fail(&mut self, state: &'static str) {
+        if let Some(c) = self.client.take() {
+            tokio::spawn(async move {
+                let _ = c.disconnect().await;
+            });
+        }
+        self.notes = None;
+        self.synced = None;
+        self.link.state = state;
+        self.retry_at = Instant::now() + self.backoff;
+        self.backoff = (self.backoff * 2).min(self.backoff_max);
+    }
+
+    /// One tick: in poll mode pull new blocks (and any tips not seen yet), then DAG info.
+    async fn poll(&mut self) -> Result<()> {
+        let client = self.client.clone().ok_or_else(|| anyhow!("not connected"))?;
+        // Sync state first, so the engine knows how to treat the blocks that follow.
+        let synced = client.get_server_info().await?.is_synced;
+        if self.synced != Some(synced) {
+            if !synced {
+                log::warn!("{}: node is not synced — its data is kept out of the statistics until it is", self.url);
+            } else if self.synced.is_some() {
+                log::info!("{}: node synced", self.url);
+            }
+            self.synced = Some(synced);
+            self.pending.push_back(SourceEvent::Sync { synced, t_ms: now_ms() });
+        }
+        let info = client.get_block_dag_info().await?;
+
+        // A node that answers but no longer advances is as good as gone: reconnect (it may be restarting).
+        // Not while it says it is syncing — IBD can hold the virtual state still for minutes — so the stale
+        // clock restarts until it is synced. Only progress counts as a good poll and resets the backoff.
+        let now = Instant::now();
+        match self.progress {
+            Some((daa, sink, _)) if daa == info.virtual_daa_score && sink == info.sink && !synced => {
+                self.progress = Some((daa, sink, now));
+            }
+            Some((daa, sink, since)) if daa == info.virtual_daa_score && sink == info.sink => {
+                if let Some(after) = self.stale_after
+                    && now - since >= after
+                {
+                    self.link.state = "stale";
+                    bail!("node stale: virtual DAA {daa} and sink unchanged for {}s", (now - since).as_secs());
+                }
+            }
+            _ => {
+                self.progress = Some((info.virtual_daa_score, info.sink, now));
+                self.link.last_good_ms = now_ms();
+                self.backoff = BACKOFF_MIN;
+            }
+        }
+
+        // Pull recent blocks: from the previous sink, or from the current sink on the first pass.
+        if self.notes.is_none() {
+            let low = self.low_hash.or(Some(info.sink));
+            let resp = client.get_blocks(low, true, false).await?;
+            let seen_ms = now_ms();
+            for b in &resp.blocks {
+                self.emit_block(b, seen_ms);
+            }
+            // get_blocks only walks up to the sink, so side-branch tips arrive once merged — fetch them
+            // now, otherwise no unordered pair could ever be seen while it is still open.
+            for tip in &info.tip_hashes {
+                if !self.emitted.contains(tip) {
+                    let b = client.get_block(*tip, false).await?;
+                    self.emit_block(&b, seen_ms);
+                }
+            }
+        }
+        self.low_hash = Some(info.sink);
+
+        // Block template right after the DAG info, so its parents are judged against the same tips. A
+        // failure here (e.g. node not synced) only skips coverage for this tick.
+        if let Some((addr, every)) = &self.template
+            && Instant::now() >= self.next_template
+        {
+            self.next_template = Instant::now() + *every;
+            match client.get_block_template(addr.clone(), b"stitchbot".to_vec()).await {
+                Ok(t) => {
+                    let parents = t.block.header.parents_by_level.first().cloned().unwrap_or_default();
+                    let parents = parents.iter().map(|&h| Hash::from(h)).collect();
+                    self.pending.push_back(SourceEvent::Template { parents, t_ms: now_ms() });
+                }
+                Err(e) => log::warn!("block template: {e}"),
+            }
+        }
+
+        self.pending.push_back(SourceEvent::Dag(DagInfo {
+            network: info.network.to_string(),
+            sink: info.sink.into(),
+            virtual_daa: info.virtual_daa_score,
+            block_count: info.block_count,
+            header_count: info.header_count,
+            difficulty: info.difficulty,
+            tips: info.tip_hashes.iter().map(|&h| Hash::from(h)).collect(),
+            t_ms: now_ms(),
+        }));
+        Ok(())
+    }
+
+    fn emit_block(&mut self, b: &RpcBlock, seen_ms: i64) {
+        if self.emitted.insert(b.header.hash) {
+            self.emitted_order.push_back(b.header.hash);
+            while self.emitted_order.len() > self.emitted_cap {
+                if let Some(old) = self.emitted_order.pop_front() {
+                    self.emitted.remove(&old);
+                }
+            }
+        }
+        let (node, merged) = block_node(b, seen_ms);
+        self.pending.push_back(SourceEvent::Block { node, merged });
+    }
+
+    fn on_notification(&mut self, n: Notification) {
+        match n {
+            Notification::BlockAdded(n) => self.emit_block(&n.block, now_ms()),
+            Notification::VirtualChainChanged(n) => self.pending.push_back(SourceEvent::ChainChanged {
+                removed: n.removed_chain_block_hashes.iter().map(|&h| Hash::from(h)).collect(),
+                added: n.added_chain_block_hashes.iter().map(|&h| Hash::from(h)).collect(),
+                t_ms: now_ms(),
+            }),
+            _ => {}
+        }
+    }
+}
+
+impl BlockSource for GrpcSource {
+    fn mode(&self) -> &'static str {
+        if self.notes.is_some() { "notify" } else { "poll" }
+    }
+
+    fn link(&self) -> Link {
+        self.link.clone()
+    }
+
+    async fn next(&mut self) -> Result<Option<SourceEvent>> {
+        loop {
+            if let Some(ev) = self.pending.pop_front() {
+                return Ok(Some(ev));
+            }
+            if self.client.is_none() {
+                self.connect().await?;
+            }
+            // Between ticks, hand pushed blocks over as they arrive (per-block first-seen).
+            if let Some(rx) = &mut self.notes {
+                let mut closed = false;
+                tokio::select! {
+                    n = rx.recv() => match n {
+                        Some(n) => {
+                            self.on_notification(n);
+                            continue;
+                        }
+                        None => closed = true,
+                    },
+                    _ = tokio::time::sleep_until(self.next_tick) => {}
+                }
+                if closed {
+                    self.fail("disconnected");
+                    bail!("notification stream closed");
+                }
+            } else {
+                tokio::time::sleep_until(self.next_tick).await;
+            }
+            // A stalled poll shouldn't queue a burst of catch-up ticks.
+            self.next_tick = (self.next_tick + self.period).max(Instant::now());
+            let res = match tokio::time::timeout(RPC_TIMEOUT, self.poll()).await {
+                Ok(r) => r,
+                Err(_) => Err(anyhow!("node did not answer within {}s", RPC_TIMEOUT.as_secs())),
+            };
+            if let Err(e) = res {
+                self.fail(if self.link.state == "stale" { "stale" } else { "disconnected" });
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Subscribe to block-added and virtual-chain-changed notifications (direct-mode listener) and forward
+/// them onto a channel that closes when the client's notification stream does.
+async fn subscribe(client: &GrpcClient) -> Result<mpsc::UnboundedReceiver<Notification>> {
+    let rx = client.notification_channel_receiver();
+    client.start_notify(ListenerId::default(), Scope::BlockAdded(BlockAddedScope {})).await?;
+    client.start_notify(ListenerId::default(), Scope::VirtualChainChanged(VirtualChainChangedScope::new(false))).await?;
+    let (tx, out) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok(n) = rx.recv().await {
+            if tx.send(n).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(out)
+}
+
+impl From<RpcHash> for Hash {
+    fn from(h: RpcHash) -> Self {
+        Hash(h.as_bytes())
+    }
+}
+
+/// Convert an `RpcBlock` into the engine's `BlockNode` plus its mergeset (blue then red hashes).
+fn block_node(b: &RpcBlock, seen_ms: i64) -> (BlockNode, Vec<Hash>) {
+    let parents = b
+        .header
+        .parents_by_level
+        .first()
+        .cloned()
+        .unwrap_or_default()
+        .iter()
+        .map(|&h| Hash::from(h))
+        .collect();
+    let vd = b.verbose_data.as_ref();
+    let merged: Vec<Hash> = vd
+        .map(|v| {
+            v.merge_set_blues_hashes
+                .iter()
+                .chain(v.merge_set_reds_hashes.iter())
+                .map(|&h| Hash::from(h))
+                .collect()
+        })
+        .unwrap_or_default();
+    let node = BlockNode {
+        hash: b.header.hash.into(),
+        blue_score: b.header.blue_score,
+        daa: b.header.daa_score,
+        timestamp: b.header.timestamp,
+        seen_ms,
+        parents,
+        selected_parent: vd.map(|v| v.selected_parent_hash.into()).unwrap_or_default(),
+        is_chain: vd.map(|v| v.is_chain_block).unwrap_or(false),
+        blues: vd.map(|v| v.merge_set_blues_hashes.len() as u32).unwrap_or(0),
+        reds: vd.map(|v| v.merge_set_reds_hashes.len() as u32).unwrap_or(0),
+    };
+    (node, merged)
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
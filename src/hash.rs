@@ -0,0 +1,152 @@
+//! Block hashes as their 32 raw bytes: `Copy` and never allocated. The engine, GHOSTDAG and the simulator
+//! key everything by them. In JSON they stay the usual 64-char hex, so files written with string hashes
+//! still load; the empty string is `Hash::ZERO` ("none", e.g. a selected parent the node didn't report).
+//! Binary formats (captures) get the raw bytes. Byte order sorts like the hex did, so every hash
+//! tie-break orders blocks exactly as before.
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Hash(pub [u8; 32]);
+
+impl Hash {
+    pub const ZERO: Hash = Hash([0; 32]);
+
+    pub fn is_zero(&self) -> bool {
+        *self == Self::ZERO
+    }
+
+    /// 64 hex digits, or "" for `ZERO`.
+    pub fn parse(s: &str) -> Option<Self> {
+        if s.is_empty() {
+            return Some(Self::ZERO);
+        }
+        let b = s.as_bytes();
+        if b.len() != 64 {
+            return None;
+        }
+        let mut out = [0u8; 32];
+        for (i, pair) in b.chunks_exact(2).enumerate() {
+            out[i] = (nibble(pair[0])? << 4) | nibble(pair[1])?;
+        }
+        Some(Hash(out))
+    }
+
+    /// The first 10 hex digits — how snapshots, the dashboard and the logs name a block ("" for `ZERO`).
+    pub fn short(&self) -> String {
+        if self.is_zero() {
+            return String::new();
+        }
+        let mut s = self.to_string();
+        s.truncate(10);
+        s
+    }
+}
+
+fn nibble(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for b in &self.0 {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.short())
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        if !s.is_human_readable() {
+            self.0.serialize(s)
+        } else if self.is_zero() {
+            s.serialize_str("")
+        } else {
+            s.collect_str(self)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        if d.is_human_readable() {
+            d.deserialize_str(HexVisitor)
+        } else {
+            <[u8; 32]>::deserialize(d).map(Hash)
+        }
+    }
+}
+
+struct HexVisitor;
+
+impl serde::de::Visitor<'_> for HexVisitor {
+    type Value = Hash;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a 64-digit hex block hash")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Hash, E> {
+        Hash::parse(s).ok_or_else(|| E::custom(format!("bad block hash {s:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEX: &str = "0123456789abcdef00ff10e0f1d2c3b4a5968778695a4b3c2d1e0f0011223344";
+
+    #[test]
+    fn parse_round_trips() {
+        let h = Hash::parse(HEX).unwrap();
+        assert_eq!(h.to_string(), HEX);
+        assert_eq!((h.0[0], h.0[31]), (0x01, 0x44));
+        assert_eq!(Hash::parse(&HEX.to_uppercase()), Some(h));
+        assert_eq!(h.short(), "0123456789");
+        assert_eq!(format!("{h:?}"), "0123456789");
+
+        let json = serde_json::to_string(&h).unwrap();
+        assert_eq!(json, format!("\"{HEX}\""));
+        assert_eq!(serde_json::from_str::<Hash>(&json).unwrap(), h);
+    }
+
+    #[test]
+    fn empty_is_zero() {
+        assert_eq!(Hash::parse(""), Some(Hash::ZERO));
+        assert!(Hash::ZERO.is_zero() && !Hash::parse(HEX).unwrap().is_zero());
+        assert_eq!(Hash::ZERO.short(), "");
+        assert_eq!(serde_json::to_string(&Hash::ZERO).unwrap(), "\"\"");
+        assert_eq!(serde_json::from_str::<Hash>("\"\"").unwrap(), Hash::ZERO);
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert_eq!(Hash::parse(&HEX[..63]), None);
+        assert_eq!(Hash::parse(&format!("{HEX}0")), None);
+        assert_eq!(Hash::parse(&HEX.replace('a', "g")), None);
+        assert!(serde_json::from_str::<Hash>("\"abc\"").is_err());
+    }
+
+    #[test]
+    fn orders_like_the_hex() {
+        let mut hs: Vec<Hash> = ["ff", "0a", "a0", "00"].iter().map(|p| Hash::parse(&p.repeat(32)).unwrap()).collect();
+        hs.sort();
+        let hex: Vec<String> = hs.iter().map(|h| h.to_string()[..2].to_string()).collect();
+        assert_eq!(hex, ["00", "0a", "a0", "ff"]);
+    }
+}
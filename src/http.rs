@@ -1,16 +1,113 @@
-//! Tiny GET-only HTTP server: `/` serves the dashboard, `/api` serves the current snapshot as JSON.
-//! Intentionally dependency-free (raw tokio) — it only ever answers two routes on a trusted LAN.
+//! Tiny GET-only HTTP server: `/` serves the dashboard, `/api` serves the current snapshot as JSON
+//! (`/api?epoch=E&since=N` with only the viz nodes changed since that snapshot),
+//! `/api/history?from=&to=&step=` downsampled series from the on-disk store, `/api/fractures` the fracture
+//! episodes, `/api/sketches` the quantile sketches behind each window (mergeable), `/api/nodes` the
+//! cross-node comparison and `/metrics` the Prometheus exposition. `/api/stream` is a Server-Sent Events
+//! stream that pushes every snapshot (and cross-node update) as it is produced — the first in full, then
+//! viz deltas — so the dashboard follows the engine instead of sampling it. Intentionally dependency-free
+//! (raw tokio) — it only ever answers a handful of routes on a trusted LAN.
 
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpListener;
-use tokio::sync::RwLock;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{RwLock, broadcast};
 
 use crate::engine::Snapshot;
+use crate::nodes::CrossNode;
+use crate::sketch::WindowStat;
 
 const DASHBOARD: &str = include_str!("dashboard.html");
+const STREAM_BACKLOG: usize = 16; // SSE frames a slow client may fall behind before it skips ahead
+const KEEPALIVE: Duration = Duration::from_secs(15);
 
-pub async fn serve(port: u16, state: Arc<RwLock<Snapshot>>) -> anyhow::Result<()> {
+/// What the server reads and the ingest tasks write.
+pub struct AppState {
+    pub snap: RwLock<Snapshot>,   // the primary node's latest snapshot
+    pub nodes: RwLock<CrossNode>, // all monitored nodes, compared
+    live: broadcast::Sender<Arc<str>>, // ready-framed SSE messages for /api/stream
+    store_path: String,                // history store /api/history reads (empty = off)
+}
+
+impl AppState {
+    pub fn new(store_path: &str) -> Self {
+        Self {
+            snap: Default::default(),
+            nodes: Default::default(),
+            live: broadcast::channel(STREAM_BACKLOG).0,
+            store_path: store_path.to_string(),
+        }
+    }
+
+    /// Make `s` the current snapshot and push it to any streaming clients.
+    pub async fn publish(&self, s: Snapshot) {
+        let mut cur = self.snap.write().await;
+        if self.live.receiver_count() > 0 {
+            let json = snapshot_json(&s, Some((cur.viz_epoch, cur.viz_seq)));
+            let _ = self.live.send(format!("data: {json}\n\n").into());
+        }
+        *cur = s;
+    }
+
+    pub async fn publish_nodes(&self, x: CrossNode) {
+        if self.live.receiver_count() > 0
+            && let Ok(json) = serde_json::to_string(&x)
+        {
+            let _ = self.live.send(format!("event: nodes\ndata: {json}\n\n").into());
+        }
+        *self.nodes.write().await = x;
+    }
+}
+
+/// The snapshot as JSON; given the (epoch, viz_seq) of a snapshot the client already holds, `nodes` carries
+/// only what changed since, with `viz_removed` and `viz_since` set. Falls back to the full set when that
+/// snapshot is out of reach.
+fn snapshot_json(s: &Snapshot, since: Option<(i64, u64)>) -> String {
+    let delta = since.and_then(|(epoch, seq)| Some((seq, s.viz_delta(epoch, seq)?)));
+    let Some((seq, d)) = delta else {
+        return serde_json::to_string(s).unwrap_or_else(|_| "{}".into());
+    };
+    let Ok(mut v) = serde_json::to_value(Snapshot { nodes: Vec::new(), viz_removed: Vec::new(), ..s.clone() }) else {
+        return "{}".into();
+    };
+    v["nodes"] = serde_json::json!(d.nodes);
+    v["viz_removed"] = serde_json::json!(d.removed);
+    v["viz_since"] = seq.into();
+    v.to_string()
+}
+
+/// `epoch` and `since` from `/api?epoch=E&since=N`.
+fn since(path: &str) -> Option<(i64, u64)> {
+    Some((param(path, "epoch")?.parse().ok()?, param(path, "since")?.parse().ok()?))
+}
+
+/// `/api/history?from=&to=&step=` — `from`/`to` in ms (default: the last 24 h up to now; at most a day
+/// ahead), `step` in seconds (default: ~720 buckets).
+async fn history(state: &AppState, path: &str) -> (&'static str, &'static str, String) {
+    if state.store_path.is_empty() {
+        return ("404 Not Found", "application/json", r#"{"error":"history store is off (store_path)"}"#.into());
+    }
+    let num = |k: &str| param(path, k).and_then(|v| v.parse::<f64>().ok());
+    // clamped to 0..=now+1d, so nothing downstream can overflow on `?from=-1e30` and the like
+    let now = chrono::Utc::now().timestamp_millis();
+    let bound = |t: i64| t.clamp(0, now + 86_400_000);
+    let to = bound(num("to").map(|v| v as i64).unwrap_or(now));
+    let from = bound(num("from").map(|v| v as i64).unwrap_or(to - 86_400_000));
+    let step_ms = num("step").map(|v| (v * 1000.0).max(0.0) as u64).unwrap_or(0);
+    let dir = state.store_path.clone();
+    match tokio::task::spawn_blocking(move || crate::store::history(&dir, from, to, step_ms)).await {
+        Ok(Ok(h)) => ("200 OK", "application/json", serde_json::to_string(&h).unwrap_or_else(|_| "{}".into())),
+        Ok(Err(e)) => ("500 Internal Server Error", "application/json", serde_json::json!({ "error": e.to_string() }).to_string()),
+        Err(e) => ("500 Internal Server Error", "application/json", serde_json::json!({ "error": e.to_string() }).to_string()),
+    }
+}
+
+fn param<'a>(path: &'a str, key: &str) -> Option<&'a str> {
+    let query = path.split_once('?')?.1;
+    query.split('&').find_map(|kv| kv.strip_prefix(key)?.strip_prefix('='))
+}
+
+pub async fn serve(port: u16, state: Arc<AppState>) -> anyhow::Result<()> {
     let listener = TcpListener::bind(("0.0.0.0", port)).await?;
     log::info!("dashboard live → http://localhost:{port}/  (bind 0.0.0.0)");
     loop {
@@ -30,19 +127,80 @@ pub async fn serve(port: u16, state: Arc<RwLock<Snapshot>>) -> anyhow::Result<()
             };
             let req = String::from_utf8_lossy(&buf[..n]);
             let path = req.split_whitespace().nth(1).unwrap_or("/");
+            let route = path.split('?').next().unwrap_or("/");
+
+            if route == "/api/stream" {
+                stream(sock, &state).await;
+                return;
+            }
 
-            let (ctype, body) = if path.starts_with("/api") {
-                let snap = state.read().await;
-                ("application/json", serde_json::to_string(&*snap).unwrap_or_else(|_| "{}".into()))
+            let (status, ctype, body) = if route == "/metrics" {
+                let snap = state.snap.read().await;
+                ("200 OK", "text/plain; version=0.0.4; charset=utf-8", crate::metrics::render(&snap))
+            } else if route == "/api/history" {
+                history(&state, path).await
+            } else if route == "/api/fractures" {
+                let snap = state.snap.read().await;
+                let body = serde_json::json!({
+                    "events": snap.fracture_events, "open": snap.fracture_open, "episodes": snap.fracture_log,
+                });
+                ("200 OK", "application/json", body.to_string())
+            } else if route == "/api/sketches" {
+                let snap = state.snap.read().await;
+                let windows = |q: &[WindowStat]| -> serde_json::Value {
+                    q.iter().map(|w| (w.window.to_string(), serde_json::json!(w.sketch))).collect::<serde_json::Map<_, _>>().into()
+                };
+                let stats: serde_json::Map<_, _> =
+                    snap.stats.iter().map(|st| (st.name.to_string(), windows(&st.windows))).collect();
+                let body = serde_json::json!({ "t": snap.updated_ms, "network": snap.network, "stats": stats });
+                ("200 OK", "application/json", body.to_string())
+            } else if route == "/api/nodes" {
+                let nodes = state.nodes.read().await;
+                ("200 OK", "application/json", serde_json::to_string(&*nodes).unwrap_or_else(|_| "{}".into()))
+            } else if route.starts_with("/api") {
+                let snap = state.snap.read().await;
+                ("200 OK", "application/json", snapshot_json(&snap, since(path)))
             } else {
-                ("text/html; charset=utf-8", DASHBOARD.to_string())
+                ("200 OK", "text/html; charset=utf-8", DASHBOARD.to_string())
             };
 
             let resp = format!(
-                "HTTP/1.1 200 OK\r\nContent-Type: {ctype}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+                "HTTP/1.1 {status}\r\nContent-Type: {ctype}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
                 body.len()
             );
             let _ = sock.write_all(resp.as_bytes()).await;
         });
     }
 }
+
+/// Hold the connection open and forward every published frame, starting with the current state.
+/// A client that falls more than `STREAM_BACKLOG` frames behind has missed viz deltas, so it is resent the
+/// full current snapshot and carries on from there.
+async fn stream(mut sock: TcpStream, state: &AppState) {
+    let mut rx = state.live.subscribe();
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nAccess-Control-Allow-Origin: *\r\nCache-Control: no-store\r\nConnection: keep-alive\r\n\r\n";
+    let first = {
+        let (snap, nodes) = (state.snap.read().await, state.nodes.read().await);
+        format!(
+            "{head}retry: 2000\ndata: {}\n\nevent: nodes\ndata: {}\n\n",
+            serde_json::to_string(&*snap).unwrap_or_else(|_| "{}".into()),
+            serde_json::to_string(&*nodes).unwrap_or_else(|_| "{}".into())
+        )
+    };
+    if sock.write_all(first.as_bytes()).await.is_err() {
+        return;
+    }
+    loop {
+        let frame: Arc<str> = match tokio::time::timeout(KEEPALIVE, rx.recv()).await {
+            Ok(Ok(f)) => f,
+            Ok(Err(broadcast::error::RecvError::Lagged(_))) => {
+                format!("data: {}\n\n", snapshot_json(&*state.snap.read().await, None)).into()
+            }
+            Ok(Err(broadcast::error::RecvError::Closed)) => return,
+            Err(_) => ": keepalive\n\n".into(), // keeps idle proxies from dropping the stream
+        };
+        if sock.write_all(frame.as_bytes()).await.is_err() {
+            return; // client went away
+        }
+    }
+}
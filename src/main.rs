@@ -1,29 +1,70 @@
 //! StitchBot — a real-time Kaspa DAG-health monitor.
 //!
-//! Polls a node over gRPC (get_block_dag_info + get_blocks), maintains a rolling DAG, computes
-//! tip-width / blue-delta / BPS / a stress index, and serves a live dashboard + JSON API.
+//! Ingests blocks from a `BlockSource` — by default a node over gRPC, polled (get_blocks) or pushed
+//! (block-added / virtual-chain-changed notifications) — maintains a rolling DAG, computes tip-width /
+//! blue-delta / BPS / a stress index, and serves a live dashboard + JSON API.
 
+mod alerts;
+mod analyze;
+mod capture;
 mod config;
 mod engine;
+mod experiment;
+mod ghostdag;
+mod grpc;
+mod hash;
 mod http;
+mod metrics;
+mod nodes;
+mod sim;
+mod sketch;
+mod source;
+mod store;
 
 use std::sync::Arc;
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use tokio::sync::RwLock;
 
-use kaspa_grpc_client::GrpcClient;
-use kaspa_rpc_core::api::rpc::RpcApi;
-use kaspa_rpc_core::RpcHash;
-
-use engine::{BlockNode, Engine, Snapshot};
+use engine::Snapshot;
+use http::AppState;
+use nodes::{Cross, NodeView};
+use source::{BlockSource, Pipeline, SourceEvent};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-    let cfg = config::Config::from_file("config.toml")?;
+    let mut cfg = config::Config::from_file("config.toml")?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // `stitchbot experiment …` — compare parent-selection policies offline (see experiment.rs); no dashboard.
+    if args.first().map(String::as_str) == Some("experiment") {
+        return experiment::run(&cfg, &args[1..]).await;
+    }
+    // `stitchbot analyze [metrics.jsonl] [--json]` — the report over a metrics log (default: log_path).
+    if args.first().map(String::as_str) == Some("analyze") {
+        return analyze::run(&cfg.log_path, &args[1..]);
+    }
+    // `stitchbot alert-test` — POST a test notification to every alert webhook and report each result.
+    if args.first().map(String::as_str) == Some("alert-test") {
+        alerts::Alerts::new(&cfg)?;
+        let body = serde_json::json!({ "alert": "test", "status": "test", "severity": "info", "t_ms": chrono::Utc::now().timestamp_millis() });
+        for url in &cfg.alert_webhooks {
+            match alerts::post(url, &body.to_string()).await {
+                Ok(()) => println!("{url}: ok"),
+                Err(e) => println!("{url}: {e}"),
+            }
+        }
+        return Ok(());
+    }
 
-    // Shared snapshot the HTTP server reads and the poll loop writes.
-    let state = Arc::new(RwLock::new(Snapshot::default()));
+    // Replayed or synthetic time must not land in the live history or fracture log.
+    if matches!(args.first().map(String::as_str), Some("replay" | "simulate")) {
+        cfg.store_path.clear();
+        cfg.fracture_log_path.clear();
+    }
+
+    // Shared state the HTTP server reads and the ingest loops write.
+    let state = Arc::new(AppState::new(&cfg.store_path));
     {
         let (st, port) = (state.clone(), cfg.http_port);
         tokio::spawn(async move {
@@ -33,119 +74,277 @@ async fn main() -> Result<()> {
         });
     }
 
-    let url = format!("grpc://{}", cfg.rpc_url);
-    log::info!("connecting to node at {url} ...");
-    let client = GrpcClient::connect(url.clone()).await.map_err(|e| anyhow!("connect {url}: {e}"))?;
-    log::info!("connected — polling every {} ms; dashboard on :{}", cfg.poll_ms, cfg.http_port);
+    // `stitchbot replay <capture> [--speed N] [--out metrics.jsonl]` — re-run a capture through the engine
+    // (speed 0 = flat out). The live dataset is left alone unless --out names a file.
+    if args.first().map(String::as_str) == Some("replay") {
+        let path = args.get(1).ok_or_else(|| anyhow::anyhow!("usage: stitchbot replay <capture> [--speed N] [--out file]"))?;
+        let speed = flag(&args, "--speed").map(|s| s.parse()).transpose()?.unwrap_or(1.0);
+        cfg.log_path = flag(&args, "--out").unwrap_or_default();
+        let src = capture::ReplaySource::open(path, speed)?;
+        log::info!("replaying {path} at {speed}× — dashboard on :{}", cfg.http_port);
+        run(src, &cfg, Some(&state), &RwLock::new(NodeView::new(path))).await?;
+        log::info!("replay finished");
+        return Ok(());
+    }
+
+    // `stitchbot simulate [--speed N] [--secs S] [--out metrics.jsonl]` — a synthetic DAG from the sim_*
+    // settings instead of a node (speed 0 = flat out; runs until --secs of simulated time, or forever).
+    if args.first().map(String::as_str) == Some("simulate") {
+        let speed = flag(&args, "--speed").map(|s| s.parse()).transpose()?.unwrap_or(1.0);
+        let secs = flag(&args, "--secs").map(|s| s.parse()).transpose()?;
+        cfg.log_path = flag(&args, "--out").unwrap_or_default();
+        let src = sim::SimSource::new(&cfg, sim::Policy::parse(&cfg.sim_policy)?, speed, secs)?;
+        log::info!(
+            "simulating λ={} BPS, Δ={} ms ({}), {} miners, ≤{} parents, k={}, policy {} — dashboard on :{}",
+            cfg.sim_bps, cfg.sim_delay_ms, cfg.sim_delay_dist, cfg.sim_miners, cfg.sim_max_parents, cfg.sim_k,
+            cfg.sim_policy, cfg.http_port
+        );
+        captured(src, &cfg, &state, &RwLock::new(NodeView::new("sim"))).await?;
+        log::info!("simulation finished");
+        return Ok(());
+    }
+
+    // Every node gets its own source + engine and publishes a view; one task compares the views.
+    let urls: Vec<String> = std::iter::once(cfg.rpc_url.clone()).chain(cfg.extra_nodes.iter().cloned()).collect();
+    let views: Arc<Vec<RwLock<NodeView>>> = Arc::new(urls.iter().map(|u| RwLock::new(NodeView::new(u))).collect());
+    {
+        let (st, views, every) = (state.clone(), views.clone(), std::time::Duration::from_millis(cfg.poll_ms));
+        tokio::spawn(async move {
+            let mut cross = Cross::default();
+            loop {
+                tokio::time::sleep(every).await;
+                let mut vs = Vec::with_capacity(views.len());
+                for v in views.iter() {
+                    vs.push(v.read().await.clone());
+                }
+                st.publish_nodes(cross.update(&vs, chrono::Utc::now().timestamp_millis())).await;
+            }
+        });
+    }
+    for (i, url) in urls.iter().enumerate().skip(1) {
+        // extra nodes: compared only — no dashboard snapshot, metrics log or capture of their own
+        let ncfg = config::Config {
+            rpc_url: url.clone(),
+            log_path: String::new(),
+            fracture_log_path: String::new(),
+            capture_path: String::new(),
+            ..cfg.clone()
+        };
+        let views = views.clone();
+        tokio::spawn(async move {
+            let res = match grpc::GrpcSource::new(&ncfg) {
+                Ok(src) => run(src, &ncfg, None, &views[i]).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
+                log::error!("node {}: {e}", ncfg.rpc_url);
+            }
+        });
+    }
+
+    let src = grpc::GrpcSource::new(&cfg)?;
+    log::info!(
+        "monitoring {} node(s), {} ingestion, snapshot every {} ms; dashboard on :{}",
+        urls.len(), cfg.ingest, cfg.poll_ms, cfg.http_port
+    );
+    captured(src, &cfg, &state, &views[0]).await
+}
+
+/// `run`, teeing the source into `capture_path` when one is configured.
+async fn captured<S: BlockSource>(src: S, cfg: &config::Config, out: &AppState, view: &RwLock<NodeView>) -> Result<()> {
+    if cfg.capture_path.is_empty() {
+        return run(src, cfg, Some(out), view).await;
+    }
+    log::info!("capturing raw events to {}", cfg.capture_path);
+    run(capture::Capture::new(src, &cfg.capture_path)?, cfg, Some(out), view).await
+}
 
-    let mut eng = Engine::new(cfg.dag_window, cfg.viz_cap);
-    let mut low_hash: Option<RpcHash> = None;
-    let mut last_count: Option<(u64, f64)> = None; // (block_count, unix_secs)
+/// Value following `name` on the command line.
+fn flag(args: &[String], name: &str) -> Option<String> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned()
+}
 
+/// Drive the engine from any block source until it is exhausted: each snapshot it yields is logged,
+/// published to `out` (the dashboard and history store; `None` for compare-only nodes), and the node's view
+/// refreshed for the cross-node comparison.
+async fn run<S: BlockSource>(mut src: S, cfg: &config::Config, out: Option<&AppState>, view: &RwLock<NodeView>) -> Result<()> {
+    let mut pipe = Pipeline::new(cfg);
+    let mut store = match out.is_some() && !cfg.store_path.is_empty() {
+        true => Some(store::Store::open(&cfg.store_path, cfg.store_days)?),
+        false => None,
+    };
+    if let Some(st) = store.as_mut() {
+        let saved = st.load_sketches(chrono::Utc::now().timestamp_millis());
+        log::info!("history store: {} saved minute sketches merged back", saved.len());
+        for (name, minute, sketch) in &saved {
+            pipe.eng.restore_sketch(name, *minute, sketch);
+        }
+    }
+    let mut alerts = match out {
+        Some(_) if !cfg.alerts.is_empty() => Some(alerts::Alerts::new(cfg)?),
+        _ => None,
+    };
+    // the sketches are saved on the way out, whether the source ends or the process is interrupted
+    let save = |store: &mut Option<store::Store>, pipe: &Pipeline| {
+        if let Some(st) = store.as_mut() {
+            st.record_sketches(&pipe.eng, chrono::Utc::now().timestamp_millis(), true);
+        }
+    };
+    let mut stop = std::pin::pin!(tokio::signal::ctrl_c());
     loop {
-        match poll_once(&client, &mut eng, &mut low_hash, &mut last_count, &cfg).await {
-            Ok(snap) => {
-                log_metrics(&cfg.log_path, &snap);
-                *state.write().await = snap;
+        let next = tokio::select! {
+            next = src.next() => next,
+            _ = &mut stop => {
+                log::info!("interrupted");
+                save(&mut store, &pipe);
+                return Ok(());
+            }
+        };
+        let ev = match next {
+            Ok(Some(ev)) => ev,
+            Ok(None) => {
+                save(&mut store, &pipe);
+                return Ok(());
             }
             Err(e) => {
-                log::warn!("poll error: {e}");
-                state.write().await.connected = false;
+                log::warn!("{}: poll error: {e}", cfg.rpc_url);
+                if let Some(out) = out {
+                    let mut s = out.snap.read().await.clone();
+                    s.connected = false;
+                    set_link(&mut s, src.link());
+                    if let Some(a) = alerts.as_mut() {
+                        a.observe(&s, chrono::Utc::now().timestamp_millis());
+                        s.alerts = a.status();
+                    }
+                    out.publish(s).await;
+                }
+                view.write().await.connected = false;
+                continue;
             }
+        };
+        // the cross-node view compares full hashes; the snapshot carries them shortened
+        let dag = match &ev {
+            SourceEvent::Dag(info) => Some((info.sink, info.tips.clone())),
+            _ => None,
+        };
+        let Some(mut s) = pipe.feed(ev, cfg) else { continue };
+        s.ingest = src.mode().to_string();
+        set_link(&mut s, src.link());
+        if let Some(a) = alerts.as_mut() {
+            a.observe(&s, s.updated_ms);
+            s.alerts = a.status();
+        }
+        log_metrics(&cfg.log_path, &s);
+        if let Some(f) = &s.fracture_ended {
+            append_json(&cfg.fracture_log_path, f);
+        }
+        if let Some(st) = store.as_mut() {
+            st.record(&s);
+            st.record_sketches(&pipe.eng, s.updated_ms, false);
+        }
+        {
+            let mut v = view.write().await;
+            v.ingest = s.ingest.clone();
+            v.connected = s.connected;
+            v.synced = s.synced;
+            (v.sink, v.tips) = dag.unwrap_or_default();
+            v.blue_max = s.blue_max;
+            v.set_chain(pipe.eng.chain());
+            v.t_ms = s.updated_ms;
+        }
+        if let Some(out) = out {
+            out.publish(s).await;
         }
-        tokio::time::sleep(std::time::Duration::from_millis(cfg.poll_ms)).await;
     }
 }
 
+fn set_link(s: &mut Snapshot, link: source::Link) {
+    s.conn_state = link.state.to_string();
+    s.reconnects = link.reconnects;
+    if link.last_good_ms > 0 {
+        s.last_good_ms = link.last_good_ms;
+    }
+}
+
+/// One line of the metrics log; field names are the keys analyze reads back.
+#[derive(serde::Serialize)]
+struct MetricsRecord<'a> {
+    t: i64,
+    net: &'a str,
+    tips: usize,
+    peak_tips: usize,
+    bps: f64,
+    blue_delta: u64,
+    max_parents: usize,
+    avg_parents: f64,
+    tip_excess: usize,
+    red_rate: f64,
+    reds: u64,
+    blues: u64,
+    merge_lat_mean: f64,
+    merge_lat_p95: f64,
+    merge_lat_max: u64,
+    merge_depth: u64,
+    depth_used_pct: f64,
+    pending_max_wait: u64,
+    orphans_new: u64,
+    orphans_evicted: u64,
+    orphans_depth: u64,
+    orphaned: &'a [engine::Orphan], // the orphans found since the previous line
+    conf_time_mean: f64,
+    conf_time_p95: f64,
+    conf_corr: f64,
+    conf_samples: usize,
+    fracture: bool,
+    fracture_proxy: bool,
+    fracture_secs: f64,
+    unordered_pairs: usize,
+    unordered_oldest_secs: f64,
+    unordered_max_gap: u64,
+    last_unordered_secs: f64,
+    template_covered: usize,
+    template_excluded: usize,
+    template_limit: &'a str,
+    template_streak_secs: f64,
+    reorg_depth: usize,
+    reorgs: u64,
+    max_reorg_depth: usize,
+    daa: u64,
+    blocks: u64,
+}
+
 /// Append one compact JSONL record per poll — the measurement dataset (scalars only, no viz nodes).
 fn log_metrics(path: &str, s: &Snapshot) {
-    use std::io::Write;
-    if !s.connected {
+    if !s.connected || !s.synced || path.is_empty() {
         return;
     }
-    let rec = serde_json::json!({
-        "t": s.updated_ms, "net": s.network, "tips": s.tip_width, "peak_tips": s.peak_tip_width,
-        "bps": s.bps, "blue_delta": s.blue_delta, "max_parents": s.max_parents, "avg_parents": s.avg_parents,
-        "tip_excess": s.tip_excess, "red_rate": s.red_rate, "reds": s.reds_window, "blues": s.blues_window,
-        "merge_lat_mean": s.merge_lat_mean, "merge_lat_p95": s.merge_lat_p95, "merge_lat_max": s.merge_lat_max,
-        "merge_depth": s.merge_depth, "depth_used_pct": s.depth_used_pct,
-        "conf_time_mean": s.conf_time_mean, "conf_time_p95": s.conf_time_p95, "conf_corr": s.conf_corr, "conf_samples": s.conf_samples,
-        "fracture": s.fracture, "fracture_secs": s.fracture_secs, "daa": s.virtual_daa, "blocks": s.block_count,
-    });
-    if let Ok(mut f) = std::fs::OpenOptions::new().create(true).append(true).open(path) {
-        let _ = writeln!(f, "{rec}");
-    }
+    let rec = MetricsRecord {
+        t: s.updated_ms, net: &s.network, tips: s.tip_width, peak_tips: s.peak_tip_width,
+        bps: s.bps, blue_delta: s.blue_delta, max_parents: s.max_parents, avg_parents: s.avg_parents,
+        tip_excess: s.tip_excess, red_rate: s.red_rate, reds: s.reds_window, blues: s.blues_window,
+        merge_lat_mean: s.merge_lat_mean, merge_lat_p95: s.merge_lat_p95, merge_lat_max: s.merge_lat_max,
+        merge_depth: s.merge_depth, depth_used_pct: s.depth_used_pct, pending_max_wait: s.pending_max_wait,
+        orphans_new: s.orphans_new, orphans_evicted: s.orphans_evicted, orphans_depth: s.orphans_depth,
+        orphaned: &s.orphans[..(s.orphans_new as usize).min(s.orphans.len())],
+        conf_time_mean: s.conf_time_mean, conf_time_p95: s.conf_time_p95, conf_corr: s.conf_corr,
+        conf_samples: s.conf_samples,
+        fracture: s.fracture, fracture_proxy: s.fracture_proxy, fracture_secs: s.fracture_secs,
+        unordered_pairs: s.unordered_pairs, unordered_oldest_secs: s.unordered_oldest_secs,
+        unordered_max_gap: s.unordered_max_gap, last_unordered_secs: s.last_unordered_secs,
+        template_covered: s.template_covered, template_excluded: s.template_excluded,
+        template_limit: s.template_limit, template_streak_secs: s.template_streak_secs,
+        reorg_depth: s.reorg_depth, reorgs: s.reorg_count, max_reorg_depth: s.max_reorg_depth,
+        daa: s.virtual_daa, blocks: s.block_count,
+    };
+    append_json(path, &rec);
 }
 
-async fn poll_once(
-    client: &GrpcClient,
-    eng: &mut Engine,
-    low_hash: &mut Option<RpcHash>,
-    last_count: &mut Option<(u64, f64)>,
-    cfg: &config::Config,
-) -> Result<Snapshot> {
-    let info = client.get_block_dag_info().await?;
-
-    // Pull recent blocks: from the previous sink, or from the current sink on the first pass.
-    let low = low_hash.or(Some(info.sink));
-    let resp = client.get_blocks(low, true, false).await?;
-    for b in &resp.blocks {
-        let parents = b
-            .header
-            .parents_by_level
-            .first()
-            .cloned()
-            .unwrap_or_default()
-            .iter()
-            .map(|h| h.to_string())
-            .collect();
-        let vd = b.verbose_data.as_ref();
-        let merged: Vec<String> = vd
-            .map(|v| {
-                v.merge_set_blues_hashes
-                    .iter()
-                    .chain(v.merge_set_reds_hashes.iter())
-                    .map(|h| h.to_string())
-                    .collect()
-            })
-            .unwrap_or_default();
-        eng.ingest(
-            BlockNode {
-                hash: b.header.hash.to_string(),
-                blue_score: b.header.blue_score,
-                daa: b.header.daa_score,
-                timestamp: b.header.timestamp,
-                parents,
-                is_chain: vd.map(|v| v.is_chain_block).unwrap_or(false),
-                blues: vd.map(|v| v.merge_set_blues_hashes.len() as u32).unwrap_or(0),
-                reds: vd.map(|v| v.merge_set_reds_hashes.len() as u32).unwrap_or(0),
-            },
-            &merged,
-        );
+fn append_json<T: serde::Serialize>(path: &str, rec: &T) {
+    use std::io::Write;
+    if path.is_empty() {
+        return;
+    }
+    if let (Ok(mut f), Ok(line)) = (std::fs::OpenOptions::new().create(true).append(true).open(path), serde_json::to_string(rec)) {
+        let _ = writeln!(f, "{line}");
     }
-    *low_hash = Some(info.sink);
-
-    // BPS from the node's total block-count delta over wall time.
-    let now = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
-    let bps = match *last_count {
-        Some((pc, pt)) => (info.block_count.saturating_sub(pc)) as f64 / (now - pt).max(0.001),
-        None => 0.0,
-    };
-    *last_count = Some((info.block_count, now));
-
-    let tips: Vec<String> = info.tip_hashes.iter().map(|h| h.to_string()).collect();
-    Ok(eng.snapshot(
-        info.network.to_string(),
-        info.sink.to_string(),
-        info.virtual_daa_score,
-        info.block_count,
-        info.header_count,
-        info.difficulty,
-        bps,
-        &tips,
-        cfg.fracture_tip_width,
-        cfg.base_min_delta,
-        cfg.merge_depth,
-        cfg.conf_depth,
-    ))
 }
@@ -0,0 +1,169 @@
+//! Prometheus text exposition (`/metrics`): every scalar in the snapshot as a gauge or counter, every
+//! windowed statistic by `window` and `quantile` label, merge latency and confirmation time as cumulative
+//! histograms, all labelled with the network.
+
+use std::fmt::Write;
+
+use crate::engine::Snapshot;
+use crate::sketch::Series;
+
+/// Merge-latency buckets, blue-score rounds (up to the 10 BPS merge depth).
+pub const MERGE_LAT_BUCKETS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 5000.0, 36000.0];
+/// Confirmation-time buckets, seconds.
+pub const CONF_TIME_BUCKETS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 15.0, 20.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+/// Cumulative-since-start histogram (the engine's own sample windows are bounded; this is not).
+#[derive(Clone, Default)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>, // per bucket, not cumulative; last = +Inf
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Self {
+        Self { bounds, counts: vec![0; bounds.len() + 1], sum: 0.0, count: 0 }
+    }
+
+    pub fn observe(&mut self, v: f64) {
+        let i = self.bounds.iter().position(|&b| v <= b).unwrap_or(self.bounds.len());
+        self.counts[i] += 1;
+        self.sum += v;
+        self.count += 1;
+    }
+}
+
+pub fn render(s: &Snapshot) -> String {
+    let mut m = Exposition { out: String::new(), net: s.network.replace(['\\', '"', '\n'], "_") };
+    m.gauge("up", "1 while the node is reachable", b(s.connected));
+    m.gauge("synced", "1 while the node reports itself synced", b(s.synced));
+    m.gauge("stale", "1 while the node answers but has stopped advancing", b(s.conn_state == "stale"));
+    m.counter("reconnects", "Reconnects to the node since start", s.reconnects as f64);
+    m.gauge("last_good_timestamp_seconds", "When the node was last seen advancing", s.last_good_ms as f64 / 1000.0);
+    m.gauge("tip_width", "Current DAG tips", s.tip_width as f64);
+    m.gauge("tip_width_peak", "Highest tip width seen", s.peak_tip_width as f64);
+    m.gauge("blocks_per_second", "Block rate from the node's block count", s.bps);
+    m.gauge("virtual_daa_score", "Virtual DAA score", s.virtual_daa as f64);
+    m.counter("blocks", "Node block count", s.block_count as f64);
+    m.counter("headers", "Node header count", s.header_count as f64);
+    m.gauge("difficulty", "Network difficulty", s.difficulty);
+    m.gauge("tip_blue_min", "Lowest tip blue score", s.blue_min as f64);
+    m.gauge("tip_blue_max", "Highest tip blue score", s.blue_max as f64);
+    m.gauge("tip_blue_delta", "Blue-score spread across tips", s.blue_delta as f64);
+    m.gauge("parents_max", "Most parents of a block in the window", s.max_parents as f64);
+    m.gauge("parents_avg", "Mean parents per block in the window", s.avg_parents);
+    m.gauge("tip_excess", "Tips beyond what one block could reference", s.tip_excess as f64);
+    m.gauge("max_block_parents", "Configured parent cap", s.max_block_parents as f64);
+    if !s.template_limit.is_empty() {
+        m.gauge("template_parents", "Parents of the latest block template", s.template_parents as f64);
+        m.gauge("template_tips_covered", "Live tips the template references", s.template_covered as f64);
+        m.gauge("template_tips_excluded", "Live tips the template leaves out", s.template_excluded as f64);
+        m.gauge("template_cap_bound", "1 if tips are left out with the template at the cap", b(s.template_limit == "cap"));
+        m.gauge("template_policy_bound", "1 if tips are left out below the cap", b(s.template_limit == "policy"));
+        m.gauge("template_exclusion_streak_seconds", "Longest current run of a tip left out", s.template_streak_secs);
+    }
+    m.gauge("red_rate", "Red share of chain-block mergesets in the window", s.red_rate);
+    m.gauge("reds_window", "Red blocks merged in the window", s.reds_window as f64);
+    m.gauge("blues_window", "Blue blocks merged in the window", s.blues_window as f64);
+    m.gauge("merge_latency_mean_rounds", "Mean merge latency, last 15 min", s.merge_lat_mean);
+    m.gauge("merge_latency_p95_rounds", "p95 merge latency, last 15 min", s.merge_lat_p95);
+    m.gauge("merge_latency_max_rounds", "Largest merge latency seen", s.merge_lat_max as f64);
+    m.histogram("merge_latency_rounds", "Merge latency in blue-score rounds", &s.merge_lat_hist);
+    m.gauge("merge_depth_rounds", "Configured merge depth", s.merge_depth as f64);
+    m.gauge("merge_depth_used_ratio", "Worst wait as a share of merge depth", s.depth_used_pct / 100.0);
+    m.gauge("pending_max_wait_rounds", "Longest wait of a block not merged yet", s.pending_max_wait as f64);
+    m.counter("orphans_evicted", "Blocks that left the window unmerged", s.orphans_evicted as f64);
+    m.counter("orphans_merge_depth", "Blocks past merge depth unmerged", s.orphans_depth as f64);
+    m.gauge("confirmation_depth_rounds", "Configured confirmation depth", s.conf_depth as f64);
+    m.gauge("confirmation_time_mean_seconds", "Mean confirmation time, last 15 min", s.conf_time_mean);
+    m.gauge("confirmation_time_p95_seconds", "p95 confirmation time, last 15 min", s.conf_time_p95);
+    m.gauge("confirmation_lag_correlation", "Correlation of merge lag and confirmation time", s.conf_corr);
+    m.histogram("confirmation_time_seconds", "Confirmation time in seconds", &s.conf_hist);
+    m.gauge("network_delay_seconds", "Measured effective propagation delay", s.delay.delay_secs);
+    m.gauge("network_delay_lower_seconds", "Lower ~95% bound of the delay", s.delay.delay_lo_secs);
+    m.gauge("network_delay_upper_seconds", "Upper ~95% bound of the delay", s.delay.delay_hi_secs);
+    m.gauge("network_delay_measured", "1 once the delay is measured, 0 while the prior is used", b(s.delay.delay_source != "prior"));
+    m.gauge("network_delay_tips_seconds", "Delay implied by mean tip width", s.delay.delay_tips_secs);
+    m.gauge("stress", "Stress index", s.stress);
+    m.gauge("stress_peak", "Highest stress index seen", s.stress_peak);
+    m.gauge("fracture", "1 while fractured", b(s.fracture));
+    m.gauge("fracture_proxy", "1 while the tip-width proxy fires", b(s.fracture_proxy));
+    m.gauge("fracture_seconds", "Length of the current fracture", s.fracture_secs);
+    m.gauge("fracture_max_seconds", "Longest fracture seen", s.max_fracture_secs);
+    m.counter("fracture_events", "Fractures started", s.fracture_events as f64);
+    m.gauge("unordered_pairs", "High-blue block pairs currently unordered", s.unordered_pairs as f64);
+    m.gauge("unordered_oldest_seconds", "Age of the oldest unordered pair", s.unordered_oldest_secs);
+    m.gauge("unordered_max_blue_gap", "Largest blue gap across unordered pairs", s.unordered_max_gap as f64);
+    m.counter("unordered_resolved", "Unordered pairs since merged", s.resolved_pairs as f64);
+    m.gauge("unordered_max_seconds", "Longest a resolved pair stayed unordered", s.max_unordered_secs);
+    m.counter("reorgs", "Selected-chain reorgs", s.reorg_count as f64);
+    m.gauge("reorg_depth", "Deepest reorg since the previous snapshot, chain blocks", s.reorg_depth as f64);
+    m.gauge("reorg_max_depth", "Deepest reorg seen, chain blocks", s.max_reorg_depth as f64);
+    m.gauge("reorg_max_blue_depth", "Deepest reorg seen, blue score", s.max_reorg_blue as f64);
+    m.gauge("window_blocks", "Blocks in the rolling window", s.window as f64);
+    m.gauge("window_seconds", "First-seen time the rolling window spans", s.window_secs);
+    for st in &s.stats {
+        m.windowed(st, &format!("{} ({}) over wall-clock windows", st.name, st.unit));
+    }
+    m.gauge("updated_timestamp_seconds", "When the snapshot was taken", s.updated_ms as f64 / 1000.0);
+    m.out
+}
+
+struct Exposition {
+    out: String,
+    net: String,
+}
+
+impl Exposition {
+    fn head(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.out, "# HELP stitchbot_{name} {help}\n# TYPE stitchbot_{name} {kind}");
+    }
+
+    fn gauge(&mut self, name: &str, help: &str, v: f64) {
+        self.head(name, help, "gauge");
+        let _ = writeln!(self.out, "stitchbot_{name}{{network=\"{}\"}} {v}", self.net);
+    }
+
+    fn counter(&mut self, name: &str, help: &str, v: f64) {
+        self.head(&format!("{name}_total"), help, "counter");
+        let _ = writeln!(self.out, "stitchbot_{name}_total{{network=\"{}\"}} {v}", self.net);
+    }
+
+    /// Sketched quantiles, one series per (window, quantile).
+    fn windowed(&mut self, st: &Series, help: &str) {
+        let name = format!("{}_window", with_unit(st.name, st.unit));
+        self.head(&name, help, "gauge");
+        for w in &st.windows {
+            for (q, v) in w.quantiles() {
+                let _ = writeln!(self.out, "stitchbot_{name}{{network=\"{}\",window=\"{}\",quantile=\"{q}\"}} {v}", self.net, w.window);
+            }
+        }
+    }
+
+    fn histogram(&mut self, name: &str, help: &str, h: &Histogram) {
+        self.head(name, help, "histogram");
+        let mut acc = 0;
+        for i in 0..=h.bounds.len() {
+            acc += h.counts.get(i).copied().unwrap_or(0);
+            let le = h.bounds.get(i).map(|b| b.to_string()).unwrap_or_else(|| "+Inf".into());
+            let _ = writeln!(self.out, "stitchbot_{name}_bucket{{network=\"{}\",le=\"{le}\"}} {acc}", self.net);
+        }
+        let _ = writeln!(self.out, "stitchbot_{name}_sum{{network=\"{}\"}} {}", self.net, h.sum);
+        let _ = writeln!(self.out, "stitchbot_{name}_count{{network=\"{}\"}} {}", self.net, h.count);
+    }
+}
+
+/// `name` with its unit appended, unless the name already says it (`bps`, `red_rate`, `fracture_secs`).
+fn with_unit(name: &str, unit: &str) -> String {
+    let base = name.strip_suffix("_secs").unwrap_or(name);
+    if base.ends_with(unit) || (unit == "ratio" && base.ends_with("_rate")) {
+        base.to_string()
+    } else {
+        format!("{base}_{unit}")
+    }
+}
+
+fn b(x: bool) -> f64 {
+    if x { 1.0 } else { 0.0 }
+}
@@ -0,0 +1,150 @@
+//! Cross-node comparison. Each monitored node runs its own source + engine and publishes a `NodeView`;
+//! `Cross` compares the latest views: how much their tip sets overlap, whether they agree on the sink,
+//! how far each lags the best blue score, and how long two nodes have been on mutually exclusive selected
+//! chains. A persistent split between nodes is the nearest observable thing to a network partition.
+
+use std::collections::HashSet;
+use serde::Serialize;
+
+use crate::engine::{round2, round4};
+use crate::hash::Hash;
+
+const CHAIN_SHARED: usize = 256; // selected-chain blocks (from the sink) each view carries
+
+/// One node's latest state, as its pipeline saw it at its last snapshot (full hashes throughout: short
+/// ones can collide).
+#[derive(Clone, Default)]
+pub struct NodeView {
+    pub url: String,
+    pub ingest: String,
+    pub connected: bool,
+    pub synced: bool,
+    pub sink: Hash,
+    pub tips: Vec<Hash>,
+    pub blue_max: u64,
+    pub chain: Vec<Hash>, // selected chain, sink first
+    pub t_ms: i64,
+}
+
+impl NodeView {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string(), ..Default::default() }
+    }
+
+    /// The selected chain, bounded.
+    pub fn set_chain(&mut self, chain: &[Hash]) {
+        self.chain = chain.iter().take(CHAIN_SHARED).copied().collect();
+    }
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct NodeStatus {
+    pub url: String,
+    pub ingest: String,
+    pub connected: bool,
+    pub sink: String,
+    pub tip_width: usize,
+    pub blue_max: u64,
+    pub blue_lag: u64,    // behind the best blue score among connected nodes
+    pub tip_overlap: f64, // Jaccard of its tips with the reference node's (0 while disconnected)
+    pub split: bool,      // on a selected chain that excludes the reference node's sink, and vice versa
+    pub age_secs: f64,    // since its last snapshot
+}
+
+/// Cross-node summary served at `/api/nodes`.
+#[derive(Serialize, Clone, Default)]
+pub struct CrossNode {
+    pub nodes: Vec<NodeStatus>,
+    pub connected: usize,
+    pub reference: String, // the node each one is compared with: the first connected, synced one
+    pub tip_overlap: f64,  // tips every connected node has / tips any has
+    pub distinct_sinks: usize,
+    pub sink_agree: bool,
+    pub max_blue_lag: u64,
+    pub chain_split: bool, // some pair of nodes each has a sink the other's selected chain doesn't contain
+    pub split_secs: f64,
+    pub max_split_secs: f64,
+    pub splits: u64,
+    pub updated_ms: i64,
+}
+
+#[derive(Default)]
+pub struct Cross {
+    split_since: Option<i64>,
+    max_split_secs: f64,
+    splits: u64,
+}
+
+impl Cross {
+    pub fn update(&mut self, views: &[NodeView], now_ms: i64) -> CrossNode {
+        let live: Vec<&NodeView> = views.iter().filter(|v| v.connected && !v.sink.is_zero()).collect();
+        let best = live.iter().map(|v| v.blue_max).max().unwrap_or(0);
+        // a disconnected or still-syncing node's tips and chain say nothing about the network
+        let reference = live.iter().find(|v| v.synced).copied();
+
+        let sets: Vec<HashSet<Hash>> = live.iter().map(|v| v.tips.iter().copied().collect()).collect();
+        let union: HashSet<Hash> = sets.iter().flatten().copied().collect();
+        let common = union.iter().filter(|h| sets.iter().all(|s| s.contains(*h))).count();
+        let sinks: HashSet<Hash> = live.iter().map(|v| v.sink).collect();
+
+        // A node merely behind has the other's sink ahead of it, but its own sink is on the other's chain.
+        // Split = neither sink is on the other's chain (within the chain tail both carry).
+        let mut chain_split = false;
+        for (i, a) in live.iter().enumerate() {
+            for b in &live[i + 1..] {
+                chain_split |= split(a, b);
+            }
+        }
+        if chain_split && self.split_since.is_none() {
+            self.split_since = Some(now_ms);
+            self.splits += 1;
+        } else if !chain_split && let Some(start) = self.split_since.take() {
+            self.max_split_secs = self.max_split_secs.max((now_ms - start) as f64 / 1000.0);
+        }
+        let split_secs = self.split_since.map(|s| (now_ms - s) as f64 / 1000.0).unwrap_or(0.0);
+
+        let nodes = views
+            .iter()
+            .map(|v| NodeStatus {
+                url: v.url.clone(),
+                ingest: v.ingest.clone(),
+                connected: v.connected,
+                sink: v.sink.short(),
+                tip_width: v.tips.len(),
+                blue_max: v.blue_max,
+                blue_lag: if v.connected { best.saturating_sub(v.blue_max) } else { 0 },
+                tip_overlap: match reference {
+                    Some(r) if v.connected => round4(jaccard(&r.tips, &v.tips)),
+                    _ => 0.0,
+                },
+                split: v.connected && reference.is_some_and(|r| split(r, v)),
+                age_secs: if v.t_ms > 0 { round2((now_ms - v.t_ms) as f64 / 1000.0) } else { 0.0 },
+            })
+            .collect();
+
+        CrossNode {
+            nodes,
+            connected: live.len(),
+            reference: reference.map(|r| r.url.clone()).unwrap_or_default(),
+            tip_overlap: if union.is_empty() { 0.0 } else { round4(common as f64 / union.len() as f64) },
+            distinct_sinks: sinks.len(),
+            sink_agree: sinks.len() <= 1,
+            max_blue_lag: live.iter().map(|v| best - v.blue_max).max().unwrap_or(0),
+            chain_split,
+            split_secs: round2(split_secs),
+            max_split_secs: round2(self.max_split_secs.max(split_secs)),
+            splits: self.splits,
+            updated_ms: now_ms,
+        }
+    }
+}
+
+fn split(a: &NodeView, b: &NodeView) -> bool {
+    a.sink != b.sink && !a.chain.contains(&b.sink) && !b.chain.contains(&a.sink)
+}
+
+fn jaccard(a: &[Hash], b: &[Hash]) -> f64 {
+    let (a, b): (HashSet<&Hash>, HashSet<&Hash>) = (a.iter().collect(), b.iter().collect());
+    let union = a.union(&b).count();
+    if union == 0 { 0.0 } else { a.intersection(&b).count() as f64 / union as f64 }
+}
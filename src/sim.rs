@@ -0,0 +1,478 @@
+//! Synthetic BlockDAG source. Blocks are mined as a Poisson process (`sim_bps`) by `sim_miners`
+//! equal-hashrate miners; every block reaches every other miner after the propagation delay of that
+//! miner-to-receiver link (drawn once per link, so some miners stay far from others for the whole run),
+//! and each miner picks parents from the tips *it* can see, under a policy and the `sim_max_parents` cap.
+//! GHOSTDAG (`ghostdag.rs`, k = `sim_k`) gives every block its blue score and mergeset. One more
+//! receiver, the observer, stands in for the node we'd be connected to: its arrivals become the `Block`,
+//! `ChainChanged` and `Dag` events the engine sees, so the simulated DAG is measured exactly like mainnet.
+//!
+//! A capture can stand in for the Poisson process (`from_capture`): its blocks are re-mined at their
+//! recorded times, each miner knowing exactly what it knew in the recording (the block's recorded past),
+//! but choosing parents from that knowledge by the policy under test — a counterfactual of the same traffic.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use anyhow::{bail, Result};
+use tokio::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::engine::{BlockNode, DagInfo};
+use crate::ghostdag::Ghostdag;
+use crate::hash::Hash;
+use crate::source::{BlockSource, SourceEvent};
+
+const KEEP: usize = 4096; // blocks (and observer chain entries) held before pruning the oldest
+const VIEW: usize = 512; // recent blocks a miner's tip set is drawn from; older ones count as referenced
+
+/// A tip as a miner sees it when choosing parents.
+pub struct Tip {
+    pub hash: Hash,
+    pub blue_score: u64,
+    pub t_ms: i64, // when it was mined
+}
+
+/// How a miner chooses parents from the tips it sees. Every policy keeps the highest-blue tip as the
+/// selected parent; they differ in which other tips fill the remaining slots under the cap.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// What the node does: tips by blue score, highest first.
+    Node,
+    /// The rest drawn uniformly from the other tips.
+    Random,
+    /// The rest lowest blue score first — the tips furthest behind the selected parent.
+    MostDivergent,
+    /// The rest oldest first — the tips that have waited longest to be merged.
+    OldestUnmerged,
+}
+
+impl Policy {
+    pub const ALL: [Policy; 4] = [Policy::Node, Policy::Random, Policy::MostDivergent, Policy::OldestUnmerged];
+
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "node" => Policy::Node,
+            "random" => Policy::Random,
+            "most_divergent" => Policy::MostDivergent,
+            "oldest_unmerged" => Policy::OldestUnmerged,
+            _ => bail!("unknown parent policy {s:?} (node | random | most_divergent | oldest_unmerged)"),
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Policy::Node => "node",
+            Policy::Random => "random",
+            Policy::MostDivergent => "most_divergent",
+            Policy::OldestUnmerged => "oldest_unmerged",
+        }
+    }
+
+    /// Up to `cap` parents from `tips`, which are sorted by (blue score, hash) descending.
+    pub fn pick(self, tips: &[Tip], cap: usize, rng: &mut Rng) -> Vec<Hash> {
+        let Some((sp, rest)) = tips.split_first() else { return Vec::new() };
+        let mut rest: Vec<&Tip> = rest.iter().collect();
+        match self {
+            Policy::Node => {}
+            Policy::Random => {
+                for i in (1..rest.len()).rev() {
+                    rest.swap(i, rng.below(i + 1));
+                }
+            }
+            Policy::MostDivergent => rest.sort_by(|a, b| (a.blue_score, &a.hash).cmp(&(b.blue_score, &b.hash))),
+            Policy::OldestUnmerged => rest.sort_by(|a, b| (a.t_ms, &a.hash).cmp(&(b.t_ms, &b.hash))),
+        }
+        std::iter::once(sp).chain(rest).take(cap.max(1)).map(|t| t.hash).collect()
+    }
+}
+
+/// Propagation-delay distribution, sampled once per (miner, receiver) link.
+#[derive(Clone, Copy)]
+enum Delay {
+    Fixed,
+    Uniform, // 0 .. 2×mean
+    Exp,
+}
+
+impl Delay {
+    fn sample(self, mean_ms: f64, rng: &mut Rng) -> i64 {
+        let d = match self {
+            Delay::Fixed => mean_ms,
+            Delay::Uniform => 2.0 * mean_ms * rng.unit(),
+            Delay::Exp => rng.exp(mean_ms),
+        };
+        d.round() as i64
+    }
+}
+
+struct SimBlock {
+    hash: Hash,
+    t_ms: i64, // sim ms it was mined
+    parents: Vec<u64>,
+    rec_parents: Vec<u64>, // parents in the recording (trace mode only)
+    arrive: Vec<i64>, // sim ms at which each receiver holds it: miners 0..n, then the observer
+    children: Vec<u64>,
+}
+
+/// A captured block awaiting re-mining.
+struct Traced {
+    hash: Hash,
+    parents: Vec<Hash>, // as recorded
+    blue_score: u64,    // as recorded — the base if none of its past is held
+    t_ms: i64,
+    seen_ms: i64,
+}
+
+pub struct SimSource {
+    gd: Ghostdag,
+    blocks: VecDeque<SimBlock>, // id = base + index
+    base: u64,
+    ids: HashMap<Hash, u64>,
+    rng: Rng,
+    prng: Rng,                          // the policy's own stream, so policies see identical traffic
+    trace: Option<VecDeque<Traced>>, // replayed schedule, topological
+    miners: usize,
+    lambda: f64, // blocks per ms
+    links: Vec<Vec<i64>>, // ms from each miner to each receiver (miners 0..n, then the observer)
+    max_parents: usize,
+    policy: Policy,
+    tick_ms: i64,
+    next_mine: i64,
+    next_tick: i64,
+    end_ms: Option<i64>,
+    arrivals: BinaryHeap<Reverse<(i64, u64)>>, // observer arrivals: (sim ms, id)
+    obs_tips: HashSet<u64>,
+    obs_count: u64,
+    chain: VecDeque<Hash>, // observer's selected chain, oldest first
+    on_chain: HashSet<Hash>,
+    pending: VecDeque<SourceEvent>,
+    start_ms: i64, // wall-clock ms that sim time 0 maps to
+    speed: f64,
+    started: Instant,
+}
+
+impl SimSource {
+    /// `speed` as for replay (≤ 0 = flat out); `secs` = simulated duration (None = forever).
+    pub fn new(cfg: &Config, policy: Policy, speed: f64, secs: Option<f64>) -> Result<Self> {
+        let delay = match cfg.sim_delay_dist.as_str() {
+            "fixed" => Delay::Fixed,
+            "uniform" => Delay::Uniform,
+            "exp" => Delay::Exp,
+            d => bail!("unknown sim_delay_dist {d:?} (fixed | uniform | exp)"),
+        };
+        if cfg.sim_bps <= 0.0 || cfg.sim_miners == 0 {
+            bail!("sim_bps and sim_miners must be positive");
+        }
+        let mut rng = Rng(cfg.sim_seed);
+        let lambda = cfg.sim_bps / 1000.0;
+        let next_mine = rng.exp(1.0 / lambda).round() as i64;
+        let n = cfg.sim_miners;
+        let links: Vec<Vec<i64>> = (0..n)
+            .map(|m| (0..=n).map(|r| if r == m { 0 } else { delay.sample(cfg.sim_delay_ms, &mut rng) }).collect())
+            .collect();
+        Ok(Self {
+            gd: Ghostdag::new(cfg.sim_k),
+            blocks: VecDeque::new(),
+            base: 0,
+            ids: HashMap::new(),
+            rng,
+            prng: Rng(cfg.sim_seed ^ 0xA5A5_5EED_0F9A_7E11),
+            trace: None,
+            miners: cfg.sim_miners,
+            lambda,
+            links,
+            max_parents: cfg.sim_max_parents,
+            policy,
+            tick_ms: cfg.poll_ms.max(1) as i64,
+            next_mine,
+            next_tick: cfg.poll_ms.max(1) as i64,
+            end_ms: secs.map(|s| (s * 1000.0) as i64),
+            arrivals: BinaryHeap::new(),
+            obs_tips: HashSet::new(),
+            obs_count: 0,
+            chain: VecDeque::new(),
+            on_chain: HashSet::new(),
+            pending: VecDeque::new(),
+            start_ms: chrono::Utc::now().timestamp_millis(),
+            speed,
+            started: Instant::now(),
+        })
+    }
+
+    /// Re-mine a capture's blocks under `policy`, as fast as possible. Each block keeps its hash, mining
+    /// time and observer arrival; its miner sees exactly the block's recorded past (what it provably
+    /// knew), and builds on the tips of that set in the re-mined DAG.
+    pub async fn from_capture(cfg: &Config, path: &str, policy: Policy) -> Result<Self> {
+        let mut seen: HashSet<Hash> = HashSet::new();
+        let mut sched: Vec<(u64, Traced)> = Vec::new();
+        let mut src = crate::capture::ReplaySource::open(path, 0.0)?;
+        while let Some(ev) = src.next().await? {
+            if let SourceEvent::Block { node, .. } = ev
+                && seen.insert(node.hash)
+            {
+                let t_ms = (node.timestamp as i64).min(node.seen_ms);
+                let tb = Traced { hash: node.hash, parents: node.parents, blue_score: node.blue_score, t_ms, seen_ms: node.seen_ms };
+                sched.push((node.daa, tb));
+            }
+        }
+        // DAA order is topological (a block's DAA score exceeds its parents'); times are then made monotone.
+        sched.sort_by(|a, b| (a.0, &a.1.hash).cmp(&(b.0, &b.1.hash)));
+        let Some(t0) = sched.iter().map(|(_, b)| b.t_ms).min() else { bail!("{path}: no blocks in capture") };
+        let end = sched.iter().map(|(_, b)| b.seen_ms).max().unwrap_or(t0) - t0;
+        let mut last = 0;
+        let trace = sched
+            .into_iter()
+            .map(|(_, mut b)| {
+                b.t_ms = (b.t_ms - t0).max(last);
+                b.seen_ms = (b.seen_ms - t0).max(b.t_ms);
+                last = b.t_ms;
+                b
+            })
+            .collect();
+        let mut sim = Self::new(&Config { sim_miners: 1, ..cfg.clone() }, policy, 0.0, Some(end as f64 / 1000.0))?;
+        sim.start_ms = t0;
+        sim.next_mine = 0;
+        sim.trace = Some(trace);
+        Ok(sim)
+    }
+
+    fn block(&self, id: u64) -> Option<&SimBlock> {
+        id.checked_sub(self.base).and_then(|i| self.blocks.get(i as usize))
+    }
+
+    /// Tips of a miner's view: recent blocks it holds none of whose children it holds.
+    fn tips_of(&self, held: impl Fn(u64, &SimBlock) -> bool) -> Vec<Tip> {
+        let from = self.blocks.len().saturating_sub(VIEW);
+        let first = self.base + from as u64;
+        let mut tips: Vec<Tip> = self
+            .blocks
+            .range(from..)
+            .zip(first..)
+            .filter(|&(b, id)| {
+                held(id, b) && !b.children.iter().any(|&c| self.block(c).is_some_and(|cb| held(c, cb)))
+            })
+            .filter_map(|(b, _)| {
+                let d = self.gd.get(&b.hash)?;
+                Some(Tip { hash: b.hash, blue_score: d.blue_score, t_ms: b.t_ms })
+            })
+            .collect();
+        tips.sort_by(|a, b| (b.blue_score, &b.hash).cmp(&(a.blue_score, &a.hash)));
+        tips
+    }
+
+    /// Recorded closed past of `from` among the recent blocks (trace mode).
+    fn recorded_past(&self, from: &[u64]) -> HashSet<u64> {
+        let floor = self.base + self.blocks.len().saturating_sub(VIEW) as u64;
+        let mut past = HashSet::new();
+        let mut stack: Vec<u64> = from.to_vec();
+        while let Some(id) = stack.pop() {
+            if id >= floor
+                && past.insert(id)
+                && let Some(b) = self.block(id)
+            {
+                stack.extend(&b.rec_parents);
+            }
+        }
+        past
+    }
+
+    fn mine(&mut self, t: i64) {
+        let traced = self.trace.as_mut().and_then(|tr| tr.pop_front());
+        let m = if traced.is_some() { 0 } else { self.rng.below(self.miners) };
+        let (hash, tips, rec_parents, base_blue) = match &traced {
+            Some(tb) => {
+                let rec: Vec<u64> = tb.parents.iter().filter_map(|p| self.ids.get(p).copied()).collect();
+                let view = self.recorded_past(&rec);
+                (tb.hash, self.tips_of(|id, _| view.contains(&id)), rec, tb.blue_score)
+            }
+            None => (self.rng.hash(), self.tips_of(|_, b| b.arrive[m] <= t), Vec::new(), 0),
+        };
+        let parents = self.policy.pick(&tips, self.max_parents, &mut self.prng);
+        let parent_ids: Vec<u64> = parents.iter().filter_map(|p| self.ids.get(p).copied()).collect();
+        self.gd.add(hash, &parents, 0, base_blue);
+
+        // Each receiver holds the block after its link's delay, and never before its parents.
+        let mut arrive = Vec::with_capacity(self.miners + 1);
+        for r in 0..=self.miners {
+            let own = match &traced {
+                Some(tb) => tb.seen_ms,
+                None => t + self.links[m][r],
+            };
+            let after = parent_ids.iter().filter_map(|p| self.block(*p)).map(|b| b.arrive[r]).max().unwrap_or(t);
+            arrive.push(own.max(after));
+        }
+        let id = self.base + self.blocks.len() as u64;
+        for p in &parent_ids {
+            let i = (p - self.base) as usize;
+            self.blocks[i].children.push(id);
+        }
+        self.arrivals.push(Reverse((arrive[self.miners], id)));
+        self.ids.insert(hash, id);
+        self.blocks.push_back(SimBlock { hash, t_ms: t, parents: parent_ids, rec_parents, arrive, children: Vec::new() });
+
+        while self.blocks.len() > KEEP {
+            if let Some(old) = self.blocks.pop_front() {
+                self.gd.remove(&old.hash);
+                self.ids.remove(&old.hash);
+                self.base += 1;
+            }
+        }
+        self.next_mine = match &self.trace {
+            Some(tr) => tr.front().map(|b| b.t_ms).unwrap_or(i64::MAX),
+            None => t + self.rng.exp(1.0 / self.lambda).round().max(1.0) as i64,
+        };
+    }
+
+    /// The observer receives block `id`: emit it, then any move of its virtual selected chain.
+    fn observe(&mut self, id: u64, t: i64) {
+        let Some(b) = self.block(id) else { return };
+        let Some(d) = self.gd.get(&b.hash) else { return };
+        let node = BlockNode {
+            hash: b.hash,
+            blue_score: d.blue_score,
+            daa: id,
+            timestamp: (self.start_ms + b.t_ms) as u64,
+            seen_ms: self.start_ms + t,
+            parents: d.parents.clone(),
+            selected_parent: d.selected_parent.unwrap_or_default(),
+            is_chain: false,
+            blues: d.blues.len() as u32,
+            reds: d.reds.len() as u32,
+        };
+        let merged = d.blues.iter().chain(d.reds.iter()).copied().collect();
+        for p in b.parents.clone() {
+            self.obs_tips.remove(&p);
+        }
+        self.obs_tips.insert(id);
+        self.obs_count += 1;
+        self.pending.push_back(SourceEvent::Block { node, merged });
+
+        let Some(sink) = self.sink() else { return };
+        if self.chain.back() == Some(&sink) {
+            return;
+        }
+        // Walk the new sink's selected parents down to the current chain; everything above that is removed.
+        let mut added = Vec::new();
+        let mut cur = Some(sink);
+        while let Some(h) = cur.take() {
+            if self.on_chain.contains(&h) {
+                cur = Some(h); // the fork point
+                break;
+            }
+            cur = self.gd.get(&h).and_then(|d| d.selected_parent);
+            added.push(h);
+        }
+        let mut removed = Vec::new();
+        while let Some(top) = self.chain.back() {
+            if cur.as_ref() == Some(top) {
+                break;
+            }
+            self.on_chain.remove(top);
+            removed.extend(self.chain.pop_back());
+        }
+        added.reverse();
+        for &h in &added {
+            self.on_chain.insert(h);
+            self.chain.push_back(h);
+        }
+        while self.chain.len() > KEEP {
+            if let Some(old) = self.chain.pop_front() {
+                self.on_chain.remove(&old);
+            }
+        }
+        self.pending.push_back(SourceEvent::ChainChanged { removed, added, t_ms: self.start_ms + t });
+    }
+
+    /// Observer's sink: its tip of highest (blue score, hash) — GHOSTDAG's selected-parent order.
+    fn sink(&self) -> Option<Hash> {
+        self.obs_tips
+            .iter()
+            .filter_map(|id| self.block(*id))
+            .filter_map(|b| self.gd.get(&b.hash).map(|d| (d.blue_score, &b.hash)))
+            .max()
+            .map(|(_, &h)| h)
+    }
+
+    fn dag_info(&self, t: i64) -> DagInfo {
+        let mut tips: Vec<Hash> =
+            self.obs_tips.iter().filter_map(|id| self.block(*id)).map(|b| b.hash).collect();
+        tips.sort();
+        DagInfo {
+            network: "simnet".to_string(),
+            sink: self.chain.back().copied().unwrap_or_default(),
+            virtual_daa: self.obs_count,
+            block_count: self.obs_count,
+            header_count: self.obs_count,
+            difficulty: 0.0,
+            tips,
+            t_ms: self.start_ms + t,
+        }
+    }
+}
+
+impl BlockSource for SimSource {
+    fn mode(&self) -> &'static str {
+        if self.trace.is_some() { "trace" } else { "sim" }
+    }
+
+    async fn next(&mut self) -> Result<Option<SourceEvent>> {
+        loop {
+            if let Some(ev) = self.pending.pop_front() {
+                return Ok(Some(ev));
+            }
+            let arrival = self.arrivals.peek().map(|Reverse((t, _))| *t).unwrap_or(i64::MAX);
+            let t = arrival.min(self.next_mine).min(self.next_tick);
+            if self.end_ms.is_some_and(|end| t > end) {
+                return Ok(None);
+            }
+            if self.speed > 0.0 {
+                tokio::time::sleep_until(self.started + Duration::from_secs_f64(t as f64 / self.speed / 1000.0)).await;
+            }
+            if t == arrival {
+                if let Some(Reverse((_, id))) = self.arrivals.pop() {
+                    self.observe(id, t);
+                }
+            } else if t == self.next_mine {
+                self.mine(t);
+            } else {
+                self.pending.push_back(SourceEvent::Dag(self.dag_info(t)));
+                self.next_tick += self.tick_ms;
+            }
+        }
+    }
+}
+
+/// SplitMix64 — small, seedable, good enough for a simulation (and keeps runs reproducible per seed).
+#[derive(Clone)]
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Exponential with the given mean.
+    pub fn exp(&mut self, mean: f64) -> f64 {
+        -mean * (1.0 - self.unit()).ln()
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    fn hash(&mut self) -> Hash {
+        let mut h = [0u8; 32];
+        for chunk in h.chunks_exact_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_be_bytes());
+        }
+        Hash(h)
+    }
+}
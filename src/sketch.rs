@@ -0,0 +1,294 @@
+//! Streaming quantile sketches. A `Sketch` buckets values on a log scale (DDSketch-style, ~1% relative
+//! error), so memory depends on the spread of the values, not their number, and two sketches merge by
+//! adding bucket counts — across windows, across nodes, or across restarts once serialized. `Windowed` keeps
+//! one sketch per wall-clock minute for the last day and answers quantiles over each of `WINDOWS`.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::round2;
+
+const GAMMA: f64 = 1.02; // bucket i holds (γ^(i-1), γ^i]: relative error (γ-1)/(γ+1) ≈ 1%
+const MIN_VALUE: f64 = 1e-9; // at or below this a value counts as zero
+pub(crate) const MINUTE_MS: i64 = 60_000;
+
+/// The windows every sketched statistic is reported over: the current minute plus this many whole
+/// minutes before it.
+pub const WINDOWS: &[(&str, i64)] = &[("1m", 1), ("15m", 15), ("1h", 60), ("24h", 1440)];
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Sketch {
+    zero: u64,
+    bins: BTreeMap<i32, u64>,
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Sketch {
+    pub fn observe(&mut self, v: f64) {
+        if v <= MIN_VALUE {
+            self.zero += 1;
+        } else {
+            *self.bins.entry((v.ln() / GAMMA.ln()).ceil() as i32).or_default() += 1;
+        }
+        let v = v.max(0.0);
+        if self.count == 0 {
+            (self.min, self.max) = (v, v);
+        } else {
+            self.min = self.min.min(v);
+            self.max = self.max.max(v);
+        }
+        self.count += 1;
+        self.sum += v;
+    }
+
+    pub fn merge(&mut self, o: &Sketch) {
+        if o.count == 0 {
+            return;
+        }
+        if self.count == 0 {
+            (self.min, self.max) = (o.min, o.max);
+        } else {
+            self.min = self.min.min(o.min);
+            self.max = self.max.max(o.max);
+        }
+        self.zero += o.zero;
+        for (&i, &c) in &o.bins {
+            *self.bins.entry(i).or_default() += c;
+        }
+        self.count += o.count;
+        self.sum += o.sum;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.count > 0 { self.sum / self.count as f64 } else { 0.0 }
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    /// Nearest-rank quantile (`q` in 0..=1), to within the bucket width; 0 when empty.
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let rank = ((self.count as f64 * q) as u64).min(self.count - 1);
+        if rank < self.zero {
+            return 0.0;
+        }
+        let mut seen = self.zero;
+        for (&i, &c) in &self.bins {
+            seen += c;
+            if seen > rank {
+                // the bucket's relative midpoint, kept inside what was actually seen
+                return (2.0 * GAMMA.powi(i) / (GAMMA + 1.0)).clamp(self.min, self.max);
+            }
+        }
+        self.max
+    }
+}
+
+/// One window of a sketched statistic, as the snapshot reports it.
+#[derive(Serialize, Clone, Default)]
+pub struct WindowStat {
+    pub window: &'static str,
+    pub count: u64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub p999: f64,
+    pub max: f64,
+    #[serde(skip)]
+    pub sketch: Sketch, // the samples behind it (`/api/sketches`)
+}
+
+impl WindowStat {
+    fn of(window: &'static str, sketch: Sketch) -> Self {
+        WindowStat {
+            window,
+            count: sketch.count(),
+            mean: round2(sketch.mean()),
+            p50: round2(sketch.quantile(0.5)),
+            p90: round2(sketch.quantile(0.9)),
+            p99: round2(sketch.quantile(0.99)),
+            p999: round2(sketch.quantile(0.999)),
+            max: round2(sketch.max()),
+            sketch,
+        }
+    }
+
+    /// Add a sample that went into the `Windowed` after this was reported, without rebuilding the report.
+    pub fn observe(&mut self, v: f64) {
+        let mut sketch = std::mem::take(&mut self.sketch);
+        sketch.observe(v);
+        *self = WindowStat::of(self.window, sketch);
+    }
+
+    /// The quantiles by Prometheus label (`1` = the max).
+    pub fn quantiles(&self) -> [(&'static str, f64); 5] {
+        [("0.5", self.p50), ("0.9", self.p90), ("0.99", self.p99), ("0.999", self.p999), ("1", self.max)]
+    }
+}
+
+/// One statistic over every window.
+#[derive(Serialize, Clone, Default)]
+pub struct Series {
+    pub name: &'static str,
+    pub unit: &'static str,
+    pub windows: Vec<WindowStat>,
+}
+
+/// Per-minute sketches for the last day. Samples are placed by their own timestamp, except that a minute
+/// already folded into the cached windows is never written again (a late sample joins the current one).
+#[derive(Default)]
+pub struct Windowed {
+    slots: VecDeque<(i64, Sketch)>, // (minute, its samples), oldest first
+    closed: Vec<Sketch>,            // per `WINDOWS` entry: its whole minutes before `closed_at`
+    closed_at: i64,                 // the minute `closed` was built in
+}
+
+impl Windowed {
+    pub fn observe(&mut self, t_ms: i64, v: f64) {
+        let m = t_ms.div_euclid(MINUTE_MS).max(self.closed_at);
+        match self.slots.back_mut() {
+            Some((last, s)) if *last >= m => s.observe(v),
+            _ => {
+                let mut s = Sketch::default();
+                s.observe(v);
+                self.slots.push_back((m, s));
+            }
+        }
+        let keep = WINDOWS.iter().map(|&(_, n)| n).max().unwrap_or(0);
+        while self.slots.front().is_some_and(|(first, _)| *first < m - keep) {
+            self.slots.pop_front();
+        }
+    }
+
+    /// The per-minute sketches of minutes `[from, to)` (minute numbers since the epoch).
+    pub fn minutes(&self, from: i64, to: i64) -> impl Iterator<Item = (i64, &Sketch)> {
+        self.slots.iter().filter(move |(m, _)| (from..to).contains(m)).map(|(m, s)| (*m, s))
+    }
+
+    /// Merge a saved minute back in (at start-up, from the store).
+    pub fn restore(&mut self, minute: i64, s: &Sketch) {
+        match self.slots.binary_search_by_key(&minute, |(m, _)| *m) {
+            Ok(i) => self.slots[i].1.merge(s),
+            Err(i) => self.slots.insert(i, (minute, s.clone())),
+        }
+        self.closed.clear(); // rebuilt by the next report
+    }
+
+    /// Every window as of `now_ms`. The whole-minute part is rebuilt once a minute; only the current
+    /// minute is merged in per call.
+    pub fn report(&mut self, now_ms: i64) -> Vec<WindowStat> {
+        let m = now_ms.div_euclid(MINUTE_MS);
+        if m > self.closed_at || self.closed.is_empty() {
+            self.closed = WINDOWS
+                .iter()
+                .map(|&(_, n)| {
+                    let mut s = Sketch::default();
+                    for (_, slot) in self.slots.iter().filter(|(sm, _)| (m - n..m).contains(sm)) {
+                        s.merge(slot);
+                    }
+                    s
+                })
+                .collect();
+            self.closed_at = self.closed_at.max(m);
+        }
+        let current: Vec<&Sketch> = self.slots.iter().rev().take_while(|(sm, _)| *sm >= m).map(|(_, s)| s).collect();
+        WINDOWS
+            .iter()
+            .zip(&self.closed)
+            .map(|(&(name, _), closed)| {
+                let mut s = closed.clone();
+                for c in &current {
+                    s.merge(c);
+                }
+                WindowStat::of(name, s)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REL: f64 = (GAMMA - 1.0) / (GAMMA + 1.0) + 1e-9;
+
+    fn sketch(values: impl IntoIterator<Item = f64>) -> Sketch {
+        let mut s = Sketch::default();
+        for v in values {
+            s.observe(v);
+        }
+        s
+    }
+
+    #[test]
+    fn quantiles_within_relative_error() {
+        let s = sketch((1..=1000).map(f64::from));
+        assert_eq!(s.count(), 1000);
+        assert!((s.mean() - 500.5).abs() < 1e-9);
+        assert_eq!(s.max(), 1000.0);
+        for (q, exact) in [(0.0, 1.0), (0.5, 501.0), (0.9, 901.0), (0.99, 991.0), (1.0, 1000.0)] {
+            let got = s.quantile(q);
+            assert!((got - exact).abs() <= exact * REL, "q{q}: {got} vs {exact}");
+        }
+        assert_eq!(Sketch::default().quantile(0.5), 0.0);
+    }
+
+    #[test]
+    fn zeros_and_negatives_count_as_zero() {
+        let s = sketch([0.0, -3.0, 0.0, 10.0]);
+        assert_eq!(s.count(), 4);
+        assert_eq!(s.quantile(0.5), 0.0);
+        assert!((s.quantile(1.0) - 10.0).abs() <= 10.0 * REL);
+        assert!((s.mean() - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn merge_matches_one_sketch() {
+        let whole = sketch((1..=500).map(|i| f64::from(i) * 0.37));
+        let mut a = sketch((1..=500).filter(|i| i % 3 == 0).map(|i| f64::from(i) * 0.37));
+        a.merge(&sketch((1..=500).filter(|i| i % 3 != 0).map(|i| f64::from(i) * 0.37)));
+        a.merge(&Sketch::default());
+        assert_eq!(a.count(), whole.count());
+        assert_eq!((a.max(), a.min), (whole.max(), whole.min));
+        assert!((a.mean() - whole.mean()).abs() < 1e-9);
+        for q in [0.1, 0.5, 0.9, 0.999] {
+            assert_eq!(a.quantile(q), whole.quantile(q));
+        }
+
+        let mut empty = Sketch::default();
+        empty.merge(&whole);
+        assert_eq!((empty.count(), empty.min, empty.max()), (whole.count(), whole.min, whole.max()));
+    }
+
+    #[test]
+    fn windows_cover_whole_minutes_and_restore() {
+        let mut w = Windowed::default();
+        let t0 = 100 * MINUTE_MS;
+        w.observe(t0 - 20 * MINUTE_MS, 7.0); // only in 1h and 24h
+        w.observe(t0 - MINUTE_MS, 3.0); // the whole minute before: every window
+        w.observe(t0 + 5_000, 1.0); // the current minute
+        let counts: Vec<u64> = w.report(t0 + 10_000).iter().map(|s| s.count).collect();
+        assert_eq!(counts, [2, 2, 3, 3]);
+
+        let mut fresh = Windowed::default();
+        for (m, s) in w.minutes(0, t0 / MINUTE_MS) {
+            fresh.restore(m, s);
+        }
+        fresh.observe(t0 + 5_000, 1.0);
+        let counts: Vec<u64> = fresh.report(t0 + 10_000).iter().map(|s| s.count).collect();
+        assert_eq!(counts, [2, 2, 3, 3]);
+    }
+}
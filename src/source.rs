@@ -0,0 +1,124 @@
+//! Block sources — anything that can drive the engine. The gRPC node is one; replay files, synthetic
+//! generators and test fixtures are others. All of them feed the same `Engine::ingest` / `snapshot`
+//! pipeline in `main::run`, so a metric behaves identically whatever produced the blocks.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::engine::{BlockNode, DagInfo, Engine, RecolourReport, Snapshot};
+use crate::hash::Hash;
+
+/// One step of input. A source emits blocks (and chain changes) as it learns of them and a `Dag` event
+/// whenever it has a fresh view of the virtual state — each `Dag` produces one snapshot. Serializable so
+/// a run can be captured and replayed (see `capture.rs`).
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "ev", rename_all = "snake_case")]
+pub enum SourceEvent {
+    Block { node: BlockNode, merged: Vec<Hash> },
+    ChainChanged { removed: Vec<Hash>, added: Vec<Hash>, t_ms: i64 },
+    /// Parents of a block template the node would hand a miner right now.
+    Template { parents: Vec<Hash>, t_ms: i64 },
+    /// The node's sync state, sent before the blocks of a tick whenever it changes. Sources that never
+    /// send it are taken as synced.
+    Sync { synced: bool, t_ms: i64 },
+    Dag(DagInfo),
+}
+
+impl SourceEvent {
+    /// Receive time of the event (ms) — what replay paces by.
+    pub fn t_ms(&self) -> i64 {
+        match self {
+            SourceEvent::Block { node, .. } => node.seen_ms,
+            SourceEvent::ChainChanged { t_ms, .. }
+            | SourceEvent::Template { t_ms, .. }
+            | SourceEvent::Sync { t_ms, .. } => *t_ms,
+            SourceEvent::Dag(info) => info.t_ms,
+        }
+    }
+}
+
+pub trait BlockSource {
+    /// Short label for the dashboard / log (`poll`, `notify`, …).
+    fn mode(&self) -> &'static str;
+
+    /// Next event, waiting as the source's pacing requires. `Ok(None)` = the source is exhausted; an
+    /// error marks the monitor disconnected until a later call succeeds.
+    async fn next(&mut self) -> anyhow::Result<Option<SourceEvent>>;
+
+    /// State of the connection behind the source. Offline sources (replay, simulator) are always up.
+    fn link(&self) -> Link {
+        Link { state: "connected", reconnects: 0, last_good_ms: 0 }
+    }
+}
+
+/// Connection health reported into each snapshot.
+#[derive(Clone)]
+pub struct Link {
+    pub state: &'static str, // "connecting" | "connected" | "stale" | "disconnected"
+    pub reconnects: u64,     // successful reconnects after the first connection
+    pub last_good_ms: i64,   // last time the node was seen advancing (0 = the snapshot's own time)
+}
+
+/// Events in, snapshots out: the engine plus the little state kept between snapshots (BPS baseline,
+/// recolour cadence). The monitor and the offline harnesses all go through this.
+pub struct Pipeline {
+    pub eng: Engine,
+    last_count: Option<(u64, f64)>, // (block_count, unix_secs)
+    snapshots: u64,
+    recolour: Vec<RecolourReport>,
+}
+
+impl Pipeline {
+    pub fn new(cfg: &Config) -> Self {
+        Self { eng: Engine::new(cfg.dag_window, cfg.viz_cap), last_count: None, snapshots: 0, recolour: Vec::new() }
+    }
+
+    /// Apply one event; a `Dag` event yields the snapshot it produces.
+    pub fn feed(&mut self, ev: SourceEvent, cfg: &Config) -> Option<Snapshot> {
+        let info = match ev {
+            SourceEvent::Block { node, merged } => {
+                self.eng.ingest(node, merged);
+                return None;
+            }
+            SourceEvent::ChainChanged { removed, added, t_ms } => {
+                self.eng.chain_changed(&removed, &added, t_ms);
+                return None;
+            }
+            SourceEvent::Template { parents, t_ms } => {
+                self.eng.template(parents, t_ms);
+                return None;
+            }
+            SourceEvent::Sync { synced, .. } => {
+                self.eng.sync_state(synced);
+                return None;
+            }
+            SourceEvent::Dag(info) => info,
+        };
+
+        // BPS from the node's total block-count delta over wall time.
+        let now = info.t_ms as f64 / 1000.0;
+        let bps = match self.last_count {
+            Some((pc, pt)) => (info.block_count.saturating_sub(pc)) as f64 / (now - pt).max(0.001),
+            None => 0.0,
+        };
+        self.last_count = Some((info.block_count, now));
+
+        let mut snap = self.eng.snapshot(
+            &info,
+            bps,
+            cfg.fracture_tip_width,
+            cfg.base_min_delta,
+            cfg.fracture_horizon,
+            cfg.fracture_unordered_ms,
+            cfg.merge_depth,
+            cfg.conf_depth,
+            cfg.max_block_parents,
+        );
+        if !cfg.recolour_k.is_empty() && self.snapshots.is_multiple_of(cfg.recolour_every.max(1)) {
+            self.recolour = self.eng.recolour(&cfg.recolour_k);
+        }
+        snap.recolour = self.recolour.clone();
+        self.snapshots += 1;
+        Some(snap)
+    }
+}
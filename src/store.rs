@@ -0,0 +1,404 @@
+//! On-disk history. Every snapshot's headline scalars, plus each reorg and each finished fracture, are
+//! appended to hourly JSONL partitions under `store_path` (`2026101714.jsonl` = that UTC hour). A query only
+//! opens the hours it spans, buckets the points by `step` and returns per-bucket mean/max series, whole-range
+//! summaries and the events — what `/api/history` serves. Partitions older than `store_days` are deleted.
+//! The engine's per-minute quantile sketches are saved too (each hour, and at shutdown) and merged back on
+//! start, so the 1 h / 24 h windows survive a restart.
+//! Deliberately plain files: nothing to install, `grep`-able, and a partition can be copied off as-is.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{Engine, Snapshot, round4};
+use crate::sketch::{MINUTE_MS, Sketch};
+
+const HOUR_MS: i64 = 3_600_000;
+const SKETCH_KEEP_MS: i64 = 86_400_000; // saved sketches reloaded: the longest window
+const MAX_BUCKETS: u64 = 720; // default step aims for this many
+const BUCKET_LIMIT: u64 = 10_000; // a requested step is widened to stay under this
+const EVENTS_SHOWN: usize = 500; // most recent reorgs / fractures per query
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "k", rename_all = "snake_case")]
+enum Record {
+    Snap(Point),
+    Reorg { t: i64, depth: usize, blue_depth: u64, old_sink: String, new_sink: String },
+    Fracture { t: i64, secs: f64 }, // written when it ends; `t` = start
+    Sketch { t: i64, name: String, sketch: Sketch }, // one statistic's samples in the minute from `t`
+}
+
+/// One snapshot's headline scalars.
+#[derive(Serialize, Deserialize)]
+struct Point {
+    t: i64,
+    tips: usize,
+    bps: f64,
+    blue_delta: u64,
+    avg_parents: f64,
+    red_rate: f64,
+    merge_lat_mean: f64,
+    merge_lat_p95: f64,
+    depth_used_pct: f64,
+    conf_time_mean: f64,
+    conf_time_p95: f64,
+    stress: f64,
+    #[serde(default)]
+    delay_secs: f64,
+    unordered: usize,
+    fracture: bool,
+    reorg_depth: usize,
+}
+
+type Field = fn(&Point) -> f64;
+
+/// The series a query returns, by name.
+const SERIES: &[(&str, Field)] = &[
+    ("tips", |p| p.tips as f64),
+    ("bps", |p| p.bps),
+    ("blue_delta", |p| p.blue_delta as f64),
+    ("avg_parents", |p| p.avg_parents),
+    ("red_rate", |p| p.red_rate),
+    ("merge_lat_mean", |p| p.merge_lat_mean),
+    ("merge_lat_p95", |p| p.merge_lat_p95),
+    ("depth_used_pct", |p| p.depth_used_pct),
+    ("conf_time_mean", |p| p.conf_time_mean),
+    ("conf_time_p95", |p| p.conf_time_p95),
+    ("stress", |p| p.stress),
+    ("delay_secs", |p| p.delay_secs),
+    ("unordered", |p| p.unordered as f64),
+    ("fracture", |p| if p.fracture { 1.0 } else { 0.0 }),
+    ("reorg_depth", |p| p.reorg_depth as f64),
+];
+
+pub struct Store {
+    dir: PathBuf,
+    keep_hours: i64,
+    hour: i64, // partition `file` belongs to
+    file: Option<File>,
+    reorgs_seen: u64, // snapshot reorg_count already stored
+    sketched_to: i64, // minutes before this one have their sketches saved
+}
+
+impl Store {
+    pub fn open(dir: &str, days: u64) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            keep_hours: (days.max(1) * 24) as i64,
+            hour: i64::MIN,
+            file: None,
+            reorgs_seen: 0,
+            sketched_to: 0,
+        })
+    }
+
+    /// Sketches saved over the last day, to hand to `Engine::restore_sketch` as (name, minute, sketch).
+    /// Everything before the current minute then counts as saved, so nothing is written twice.
+    pub fn load_sketches(&mut self, now_ms: i64) -> Vec<(String, i64, Sketch)> {
+        let (from, to) = (now_ms - SKETCH_KEEP_MS, now_ms.div_euclid(MINUTE_MS) * MINUTE_MS);
+        self.sketched_to = now_ms.div_euclid(MINUTE_MS);
+        let Ok(dir) = fs::read_dir(&self.dir) else { return Vec::new() };
+        let mut parts: Vec<(i64, PathBuf)> = dir
+            .flatten()
+            .filter_map(|e| Some((hour_of(&e.path())?, e.path())))
+            .filter(|(h, _)| (from.div_euclid(HOUR_MS)..=now_ms.div_euclid(HOUR_MS)).contains(h))
+            .collect();
+        parts.sort_unstable();
+        let mut out = Vec::new();
+        for (_, path) in parts {
+            let Ok(f) = File::open(path) else { continue };
+            for line in BufReader::new(f).lines().map_while(|l| l.ok()) {
+                if let Ok(Record::Sketch { t, name, sketch }) = serde_json::from_str(&line)
+                    && (from..to).contains(&t)
+                {
+                    out.push((name, t.div_euclid(MINUTE_MS), sketch));
+                }
+            }
+        }
+        out
+    }
+
+    /// Save the per-minute sketches closed since the last save: on the first snapshot of a new hour, or
+    /// whenever `force`d (shutdown). Only whole minutes are written; the current one is left to the next.
+    pub fn record_sketches(&mut self, eng: &Engine, now_ms: i64, force: bool) {
+        let m = now_ms.div_euclid(MINUTE_MS);
+        let same_hour = m.div_euclid(60) == self.sketched_to.div_euclid(60);
+        if m <= self.sketched_to || (same_hour && !force) {
+            return;
+        }
+        let recs: Vec<Record> = eng
+            .sketch_minutes(self.sketched_to, m)
+            .map(|(name, minute, sketch)| Record::Sketch { t: minute * MINUTE_MS, name: name.into(), sketch: sketch.clone() })
+            .collect();
+        self.sketched_to = m;
+        if let Err(e) = self.append(now_ms, &recs) {
+            log::warn!("history store {}: {e}", self.dir.display());
+        }
+    }
+
+    /// Append a snapshot's point and any reorgs / fracture end it reports (nothing while the node is
+    /// syncing). Disk errors are logged, not fatal — the monitor keeps running without history.
+    pub fn record(&mut self, s: &Snapshot) {
+        if !s.connected || !s.synced {
+            return;
+        }
+        let mut recs = vec![Record::Snap(Point {
+            t: s.updated_ms,
+            tips: s.tip_width,
+            bps: s.bps,
+            blue_delta: s.blue_delta,
+            avg_parents: s.avg_parents,
+            red_rate: s.red_rate,
+            merge_lat_mean: s.merge_lat_mean,
+            merge_lat_p95: s.merge_lat_p95,
+            depth_used_pct: s.depth_used_pct,
+            conf_time_mean: s.conf_time_mean,
+            conf_time_p95: s.conf_time_p95,
+            stress: s.stress,
+            delay_secs: s.delay.delay_secs,
+            unordered: s.unordered_pairs,
+            fracture: s.fracture,
+            reorg_depth: s.reorg_depth,
+        })];
+        let new = s.reorg_count.saturating_sub(self.reorgs_seen) as usize;
+        self.reorgs_seen = s.reorg_count;
+        for r in s.reorgs.iter().take(new).rev() {
+            recs.push(Record::Reorg {
+                t: r.t_ms,
+                depth: r.depth,
+                blue_depth: r.blue_depth,
+                old_sink: r.old_sink.clone(),
+                new_sink: r.new_sink.clone(),
+            });
+        }
+        if let Some(f) = &s.fracture_ended {
+            recs.push(Record::Fracture { t: f.start_ms, secs: f.secs });
+        }
+        if let Err(e) = self.append(s.updated_ms, &recs) {
+            log::warn!("history store {}: {e}", self.dir.display());
+        }
+    }
+
+    fn append(&mut self, t_ms: i64, recs: &[Record]) -> Result<()> {
+        let hour = t_ms.div_euclid(HOUR_MS);
+        if hour != self.hour || self.file.is_none() {
+            let path = self.dir.join(partition(hour));
+            self.file = Some(OpenOptions::new().create(true).append(true).open(path)?);
+            self.hour = hour;
+            self.prune();
+        }
+        let mut buf = String::new();
+        for r in recs {
+            buf.push_str(&serde_json::to_string(r)?);
+            buf.push('\n');
+        }
+        if let Some(f) = self.file.as_mut() {
+            f.write_all(buf.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn prune(&self) {
+        let Ok(dir) = fs::read_dir(&self.dir) else { return };
+        for e in dir.flatten() {
+            if hour_of(&e.path()).is_some_and(|h| h <= self.hour - self.keep_hours) {
+                let _ = fs::remove_file(e.path());
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct History {
+    pub from: i64,
+    pub to: i64,
+    pub step_secs: f64,
+    pub samples: usize,
+    pub t: Vec<i64>, // bucket starts
+    pub series: BTreeMap<&'static str, Series>,
+    pub summary: BTreeMap<&'static str, Summary>, // over every point in range, not the buckets
+    pub reorgs: Vec<StoredReorg>,
+    pub fractures: Vec<StoredFracture>,
+}
+
+/// Per-bucket mean and max (`null` where the bucket has no points — monitor down or disconnected).
+#[derive(Serialize, Default)]
+pub struct Series {
+    pub mean: Vec<Option<f64>>,
+    pub max: Vec<Option<f64>>,
+}
+
+#[derive(Serialize)]
+pub struct Summary {
+    pub min: f64,
+    pub mean: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+#[derive(Serialize)]
+pub struct StoredReorg {
+    pub t_ms: i64,
+    pub depth: usize,
+    pub blue_depth: u64,
+    pub old_sink: String,
+    pub new_sink: String,
+}
+
+#[derive(Serialize)]
+pub struct StoredFracture {
+    pub t_ms: i64,
+    pub secs: f64,
+}
+
+/// Points and events in `[from, to)`, bucketed by `step_ms` (0 = pick one giving ~`MAX_BUCKETS`).
+pub fn history(dir: &str, from: i64, to: i64, step_ms: u64) -> Result<History> {
+    let to = to.max(from.saturating_add(1));
+    let span = to.abs_diff(from);
+    let step = match step_ms {
+        0 => span.div_ceil(MAX_BUCKETS).div_ceil(1000) * 1000,
+        s => s.max(span.div_ceil(BUCKET_LIMIT)),
+    }
+    .max(1);
+    let n = span.div_ceil(step) as usize;
+
+    let mut points = Vec::new();
+    let (mut reorgs, mut fractures) = (Vec::new(), Vec::new());
+    let (first, last) = (from.div_euclid(HOUR_MS), (to - 1).div_euclid(HOUR_MS));
+    let mut parts: Vec<(i64, PathBuf)> = fs::read_dir(dir)?
+        .flatten()
+        .filter_map(|e| Some((hour_of(&e.path())?, e.path())))
+        .filter(|(h, _)| (first..=last).contains(h))
+        .collect();
+    parts.sort_unstable();
+    for (_, path) in parts {
+        let Ok(f) = File::open(path) else { continue };
+        for line in BufReader::new(f).lines() {
+            let Ok(rec) = serde_json::from_str::<Record>(&line?) else { continue }; // torn last line
+            match rec {
+                Record::Snap(p) if p.t >= from && p.t < to => points.push(p),
+                Record::Reorg { t, depth, blue_depth, old_sink, new_sink } if t >= from && t < to => {
+                    reorgs.push(StoredReorg { t_ms: t, depth, blue_depth, old_sink, new_sink })
+                }
+                Record::Fracture { t, secs } if t < to && t.saturating_add((secs * 1000.0) as i64) >= from => {
+                    fractures.push(StoredFracture { t_ms: t, secs })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut series = BTreeMap::new();
+    let mut summary = BTreeMap::new();
+    for &(name, get) in SERIES {
+        let (mut sum, mut cnt, mut max) = (vec![0.0; n], vec![0u32; n], vec![f64::MIN; n]);
+        let mut all = Vec::with_capacity(points.len());
+        for p in &points {
+            let (i, v) = ((p.t.abs_diff(from) / step) as usize, get(p));
+            sum[i] += v;
+            cnt[i] += 1;
+            max[i] = max[i].max(v);
+            all.push(v);
+        }
+        let s = Series {
+            mean: (0..n).map(|i| (cnt[i] > 0).then(|| round4(sum[i] / cnt[i] as f64))).collect(),
+            max: (0..n).map(|i| (cnt[i] > 0).then(|| round4(max[i]))).collect(),
+        };
+        series.insert(name, s);
+        if !all.is_empty() {
+            all.sort_by(|a, b| a.total_cmp(b));
+            summary.insert(
+                name,
+                Summary {
+                    min: round4(all[0]),
+                    mean: round4(all.iter().sum::<f64>() / all.len() as f64),
+                    p95: round4(all[(all.len() * 95 / 100).min(all.len() - 1)]),
+                    max: round4(all[all.len() - 1]),
+                },
+            );
+        }
+    }
+
+    let skip = |len: usize| len.saturating_sub(EVENTS_SHOWN);
+    Ok(History {
+        from,
+        to,
+        step_secs: step as f64 / 1000.0,
+        samples: points.len(),
+        t: (0..n as u64).map(|i| from.saturating_add_unsigned(i * step)).collect(),
+        series,
+        summary,
+        reorgs: reorgs.split_off(skip(reorgs.len())),
+        fractures: fractures.split_off(skip(fractures.len())),
+    })
+}
+
+/// Partition file name for an hour index (hours since the epoch, UTC).
+fn partition(hour: i64) -> String {
+    match chrono::DateTime::from_timestamp(hour * 3600, 0) {
+        Some(t) => t.format("%Y%m%d%H.jsonl").to_string(),
+        None => format!("{hour}.jsonl"),
+    }
+}
+
+fn hour_of(path: &Path) -> Option<i64> {
+    if path.extension()? != "jsonl" {
+        return None;
+    }
+    let stem = path.file_stem()?.to_str()?;
+    let t = chrono::NaiveDateTime::parse_from_str(&format!("{stem}0000"), "%Y%m%d%H%M%S").ok()?;
+    Some(t.and_utc().timestamp().div_euclid(3600))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const T0: i64 = 472_222 * HOUR_MS; // an hour boundary
+
+    fn store_with(points: &[(i64, usize)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("stitchbot-store-{}-{}", std::process::id(), points.len()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut st = Store::open(dir.to_str().unwrap(), 7).unwrap();
+        for &(t, tips) in points {
+            st.record(&Snapshot { updated_ms: t, tip_width: tips, connected: true, synced: true, ..Default::default() });
+        }
+        dir
+    }
+
+    #[test]
+    fn buckets_by_step() {
+        let dir = store_with(&[(T0 - 1000, 99), (T0, 2), (T0 + 10_000, 4), (T0 + 70_000, 6), (T0 + HOUR_MS + 5_000, 10)]);
+        let h = history(dir.to_str().unwrap(), T0, T0 + 120_000, 60_000).unwrap();
+        assert_eq!((h.samples, h.step_secs), (3, 60.0));
+        assert_eq!(h.t, [T0, T0 + 60_000]);
+        let tips = &h.series["tips"];
+        assert_eq!(tips.mean, [Some(3.0), Some(6.0)]);
+        assert_eq!(tips.max, [Some(4.0), Some(6.0)]);
+        let s = &h.summary["tips"];
+        assert_eq!((s.min, s.mean, s.max), (2.0, 4.0, 6.0));
+
+        // across both hourly partitions, empty buckets in between
+        let h = history(dir.to_str().unwrap(), T0, T0 + HOUR_MS + 60_000, 600_000).unwrap();
+        assert_eq!((h.samples, h.t.len()), (4, 7));
+        assert_eq!(h.series["tips"].mean[1], None);
+        assert_eq!(h.series["tips"].mean[6], Some(10.0));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn extreme_ranges_do_not_overflow() {
+        let dir = store_with(&[(T0, 2), (T0 + 1000, 3)]);
+        let path = dir.to_str().unwrap();
+        let h = history(path, i64::MIN, i64::MAX, 0).unwrap();
+        assert_eq!((h.samples, h.t.len()), (2, 720));
+        let h = history(path, i64::MAX, i64::MAX, 0).unwrap(); // empty, once `to` can't move past `from`
+        assert_eq!((h.samples, h.t.len()), (0, 0));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}